@@ -0,0 +1,43 @@
+use std::error::Error;
+use std::process;
+
+use aoc_2019::intcode::patch::PatchSet;
+use aoc_2019::intcode::program::Program;
+use aoc_2019::intcode::IntCode;
+
+// Lists the presets of a patch file and, if a program is given, checks them against it.
+fn run(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let patches = PatchSet::from_file(&args[0])?;
+    let vm = match args.get(1) {
+        Some(path) => Some(IntCode::new(&Program::from_file(path)?)),
+        None => None,
+    };
+
+    for preset in patches.presets() {
+        match vm.as_ref().map(|vm| preset.check(vm)) {
+            Some(Ok(())) => println!("[{}] ok", preset.name),
+            Some(Err(e)) => println!("[{}] {}", preset.name, e),
+            None => println!("[{}]", preset.name),
+        }
+
+        for patch in &preset.patches {
+            println!("    {patch}");
+        }
+    }
+
+    Ok(())
+}
+
+fn main() {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+
+    if args.is_empty() || args.len() > 2 {
+        eprintln!("usage: intcode-patches <patch file> [program file]");
+        process::exit(2);
+    }
+
+    if let Err(e) = run(&args) {
+        eprintln!("{e}");
+        process::exit(1);
+    }
+}
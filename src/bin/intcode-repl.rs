@@ -0,0 +1,244 @@
+use std::convert::TryFrom;
+use std::error::Error;
+use std::io::{self, BufRead, Write};
+use std::process;
+
+use aoc_2019::intcode::program::Program;
+use aoc_2019::intcode::symbols::SymbolTable;
+use aoc_2019::intcode::{IntCode, State};
+
+// Script files contain one line per entry, as if typed, with `#` starting a comment line.
+const USAGE: &str = "usage: intcode-repl <program file> [--script <file>] [--symbols <file>]";
+
+const HELP: &str = "\
+Every line that doesn't start with ':' or '!' is sent to the program, followed by a newline.
+  !!              repeat the last line
+  !<n>            repeat line <n> of the history
+  :history        list all lines sent so far
+  :save <file>    write a snapshot of the VM
+  :load <file>    replace the VM with a snapshot
+  :undo           go back to before the last line (or :load)
+  :trace [on|off] print every instruction to stderr while it executes
+  :disasm <start> <end>
+                  disassemble the given address range
+  :symbols <file> name addresses after a symbol file in :trace and :disasm
+  :help           show this text
+  :quit           exit";
+
+struct Repl {
+    vm: IntCode,
+    history: Vec<String>,
+    // Each VM snapshot, with whether the step it undoes also added a history line.
+    undo: Vec<(IntCode, bool)>,
+    trace: bool,
+    symbols: SymbolTable,
+    at_line_start: bool,
+}
+
+impl Repl {
+    fn new(vm: IntCode) -> Self {
+        Self {
+            vm,
+            history: Vec::new(),
+            undo: Vec::new(),
+            trace: false,
+            symbols: SymbolTable::default(),
+            at_line_start: true,
+        }
+    }
+
+    // Runs until the VM asks for input or halts, echoing everything it writes.
+    fn run(&mut self) -> State {
+        loop {
+            let state = if self.trace {
+                if let Some(instruction) = self.vm.current_instruction() {
+                    eprintln!(
+                        "{:>5}: {}",
+                        self.symbols.label(instruction.addr),
+                        instruction.annotate(&self.symbols)
+                    );
+                }
+                self.vm.step()
+            } else {
+                Some(self.vm.run())
+            };
+
+            match state {
+                Some(State::Write(n)) => self.print_output(n),
+                Some(state) => return state,
+                None => {}
+            }
+        }
+    }
+
+    // ASCII goes to the terminal as is, anything else on a line of its own.
+    fn print_output(&mut self, val: i64) {
+        if let Some(ch) = u8::try_from(val).ok().filter(u8::is_ascii) {
+            print!("{}", char::from(ch));
+            self.at_line_start = ch == b'\n';
+        } else {
+            if !self.at_line_start {
+                println!();
+            }
+            println!(">> {val}");
+            self.at_line_start = true;
+        }
+    }
+
+    fn send(&mut self, line: &str) {
+        if self.vm.is_halted() {
+            println!("[program halted, use :undo or :load]");
+            return;
+        }
+
+        self.undo.push((self.vm.clone(), true));
+        self.history.push(line.to_owned());
+
+        for ch in line.chars().chain(std::iter::once('\n')) {
+            match self.run() {
+                State::Waiting => self.vm.input(i64::from(u32::from(ch))),
+                State::Halted(_) => break,
+                State::Write(_) => unreachable!("run never stops on a write"),
+            }
+        }
+
+        if let State::Halted(_) = self.run() {
+            println!("[program halted]");
+        }
+    }
+
+    // Returns `false` once the session should end.
+    fn command(&mut self, line: &str) -> Result<bool, Box<dyn Error>> {
+        let mut parts = line.split_whitespace();
+        let cmd = parts.next().unwrap_or_default();
+        let arg = parts.next();
+
+        match (cmd, arg) {
+            (":disasm", Some(start)) => {
+                let start = start.parse::<usize>()?;
+                let end = parts.next().ok_or(USAGE)?.parse::<usize>()?;
+                print!("{}", self.vm.disassemble_with(start..end, &self.symbols));
+            }
+            (":symbols", Some(path)) => {
+                self.symbols = SymbolTable::from_file(path)?;
+                println!("[{} symbols loaded]", self.symbols.symbols().len());
+            }
+            (":help", _) => println!("{HELP}"),
+            (":quit", _) => return Ok(false),
+            (":history", _) => {
+                for (idx, line) in self.history.iter().enumerate() {
+                    println!("{:>4}  {line}", idx + 1);
+                }
+            }
+            (":save", Some(path)) => {
+                self.vm.save_to_file(path)?;
+                println!("[saved to {path}]");
+            }
+            (":load", Some(path)) => {
+                let vm = IntCode::load_from_file(path)?;
+                self.undo.push((std::mem::replace(&mut self.vm, vm), false));
+                println!("[loaded {path}]");
+                self.run();
+            }
+            (":undo", _) => match self.undo.pop() {
+                Some((vm, sent)) => {
+                    self.vm = vm;
+                    if sent {
+                        self.history.pop();
+                    }
+                    println!("[undone]");
+                }
+                None => println!("[nothing to undo]"),
+            },
+            (":trace", None | Some("on" | "off")) => {
+                self.trace = arg.map_or(!self.trace, |it| it == "on");
+                println!("[trace {}]", if self.trace { "on" } else { "off" });
+            }
+            _ => println!("[unknown command {line:?}, see :help]"),
+        }
+
+        Ok(true)
+    }
+
+    // Expands `!!` and `!<n>` from the history.
+    fn recall(&self, line: &str) -> Option<String> {
+        let idx = match line.strip_prefix('!')? {
+            "!" => self.history.len(),
+            n => n.parse().ok()?,
+        };
+
+        self.history.get(idx.checked_sub(1)?).cloned()
+    }
+}
+
+fn run(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let program = Program::from_file(&args[0])?;
+    let mut repl = Repl::new(IntCode::new(&program));
+    let mut script = String::new();
+
+    for option in args[1..].chunks(2) {
+        match option {
+            [flag, path] if flag == "--script" => script = std::fs::read_to_string(path)?,
+            [flag, path] if flag == "--symbols" => repl.symbols = SymbolTable::from_file(path)?,
+            _ => return Err(USAGE.into()),
+        }
+    }
+
+    repl.run();
+
+    let stdin = io::stdin();
+    let scripted = script
+        .lines()
+        .filter(|it| !it.starts_with('#'))
+        .map(|it| (it.to_owned(), true));
+    let typed = stdin
+        .lock()
+        .lines()
+        .map_while(Result::ok)
+        .map(|it| (it, false));
+
+    for (line, from_script) in scripted.chain(typed) {
+        if from_script {
+            println!("> {line}");
+        }
+
+        let line = if line.starts_with('!') {
+            let Some(recalled) = repl.recall(&line) else {
+                println!("[no such history entry]");
+                continue;
+            };
+            println!("> {recalled}");
+            recalled
+        } else {
+            line
+        };
+
+        if line.starts_with(':') {
+            match repl.command(&line) {
+                Ok(true) => {}
+                Ok(false) => break,
+                Err(e) => println!("[{e}]"),
+            }
+        } else {
+            repl.send(&line);
+        }
+
+        io::stdout().flush()?;
+    }
+
+    Ok(())
+}
+
+fn main() {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+
+    if args.is_empty() {
+        eprintln!("{USAGE}");
+        process::exit(2);
+    }
+
+    if let Err(e) = run(&args) {
+        eprintln!("{e}");
+        process::exit(1);
+    }
+}
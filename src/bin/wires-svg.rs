@@ -0,0 +1,26 @@
+use std::error::Error;
+use std::process;
+
+use aoc_2019::day03::{generate, to_svg};
+
+// Draws the wires of a day 3 input, one per line, as SVG on stdout.
+fn run(path: &str) -> Result<(), Box<dyn Error>> {
+    let inp = std::fs::read_to_string(path)?;
+    print!("{}", to_svg(&generate(&inp)));
+
+    Ok(())
+}
+
+fn main() {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+
+    if args.len() != 1 {
+        eprintln!("usage: wires-svg <wire file>");
+        process::exit(2);
+    }
+
+    if let Err(e) = run(&args[0]) {
+        eprintln!("{e}");
+        process::exit(1);
+    }
+}
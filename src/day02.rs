@@ -1,40 +1,73 @@
 use aoc_runner_derive::{aoc, aoc_generator};
-use itertools::iproduct;
 
+use crate::intcode::patch::{PatchError, PatchSet};
+use crate::intcode::program::{ParseError, Program};
+use crate::intcode::search::find;
 use crate::intcode::{IntCode, State};
 
+const PATCHES: &str = include_str!("../patches/day02.patch");
+
 #[aoc_generator(day2)]
-pub fn generate(inp: &str) -> Vec<i64> {
-    inp.split(',').filter_map(|it| it.parse().ok()).collect()
+pub fn generate(inp: &str) -> Result<Program, ParseError> {
+    Program::parse(inp)
 }
 
 #[aoc(day2, part1)]
-pub fn part1(v: &[i64]) -> i64 {
-    let mut input = v.to_owned();
-    input[1] = 12;
-    input[2] = 2;
+pub fn part1(v: &[i64]) -> Result<i64, PatchError> {
+    let mut vm = IntCode::new(v);
+    PatchSet::parse(PATCHES)?.apply("1202 program alarm", &mut vm)?;
 
-    let mut vm = IntCode::new(&input);
     if let State::Halted(num) = vm.run() {
-        return num;
+        return Ok(num);
     }
 
     unreachable!("VM did not halt!");
 }
 
-#[aoc(day2, part2)]
-pub fn part2(v: &[i64]) -> i64 {
-    for (first, second) in iproduct!(0..=99, 0..=99) {
-        let mut inp = v.to_owned();
-        inp[1] = first;
-        inp[2] = second;
+const TARGET: i64 = 19_690_720;
+
+fn run_with(v: &[i64], noun: i64, verb: i64) -> Option<i64> {
+    let mut vm = IntCode::new(v);
+    vm.init_ram(1, noun);
+    vm.init_ram(2, verb);
+
+    match vm.run() {
+        State::Halted(num) => Some(num),
+        _ => None,
+    }
+}
 
-        let mut vm = IntCode::new(&inp);
+// The gravity assist programs compute `a * noun + b * verb + c`. The coefficients are fitted
+// from three runs and checked on a few more, `None` if the program isn't linear after all.
+fn solve_linear(v: &[i64], target: i64) -> Option<(i64, i64)> {
+    let c = run_with(v, 0, 0)?;
+    let a = run_with(v, 1, 0)? - c;
+    let b = run_with(v, 0, 1)? - c;
 
-        if vm.run() == State::Halted(19_690_720) {
-            return first * 100 + second;
-        }
+    let is_linear = [(1, 1), (42, 17), (99, 99)]
+        .iter()
+        .all(|&(noun, verb)| run_with(v, noun, verb) == Some(a * noun + b * verb + c));
+    if !is_linear || b == 0 {
+        return None;
     }
 
-    unreachable!("Result not found!");
+    (0..=99)
+        .find_map(|noun| {
+            let rest = target - c - a * noun;
+            let verb = rest / b;
+            (rest % b == 0 && (0..=99).contains(&verb)).then_some((noun, verb))
+        })
+        .filter(|&(noun, verb)| run_with(v, noun, verb) == Some(target))
+}
+
+#[aoc(day2, part2)]
+pub fn part2(v: &[i64]) -> Option<i64> {
+    let (noun, verb) = solve_linear(v, TARGET).or_else(|| {
+        let found = find(v, &[(1, 0..=99), (2, 0..=99)], |it| {
+            it.state == State::Halted(TARGET)
+        });
+        found.ok().flatten().map(|it| (it[0], it[1]))
+    })?;
+
+    Some(noun * 100 + verb)
 }
@@ -1,4 +1,5 @@
-use std::collections::HashSet;
+use std::collections::BTreeMap;
+use std::fmt::Write;
 
 use aoc_runner_derive::{aoc, aoc_generator};
 use itertools::Itertools;
@@ -22,73 +23,385 @@ pub enum CableSection {
 #[derive(Copy, Clone, PartialOrd, Ord, PartialEq, Eq, Hash, Debug)]
 pub struct Position(i64, i64);
 
-fn get_coords(wire: &str) -> Vec<Position> {
-    wire.split(',')
-        .filter_map(|it| it.parse::<CableSection>().ok())
-        .fold(Vec::new(), |mut acc, it| {
-            let last_pos = *acc.last().unwrap_or(&Position(0, 0));
-
-            let positions = match it {
-                CableSection::Right(n) => (1..=n)
-                    .map(|it| Position(last_pos.0 + it, last_pos.1))
-                    .collect_vec(),
-                CableSection::Down(n) => (1..=n)
-                    .map(|it| Position(last_pos.0, last_pos.1 - it))
-                    .collect_vec(),
-                CableSection::Left(n) => (1..=n)
-                    .map(|it| Position(last_pos.0 - it, last_pos.1))
-                    .collect_vec(),
-                CableSection::Up(n) => (1..=n)
-                    .map(|it| Position(last_pos.0, last_pos.1 + it))
-                    .collect_vec(),
-            };
-
-            acc.extend(positions);
-            acc
-        })
+impl Position {
+    const fn distance(self) -> i64 {
+        self.0.abs() + self.1.abs()
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct Segment {
+    start: Position,
+    end: Position,
+    // Steps along the wire up to `start`.
+    steps: i64,
+}
+
+impl Segment {
+    const fn is_horizontal(&self) -> bool {
+        self.start.1 == self.end.1
+    }
+
+    // The range the segment covers along its direction, and its coordinate on the other axis.
+    fn span(&self) -> (i64, i64, i64) {
+        let (Position(x1, y1), Position(x2, y2)) = (self.start, self.end);
+
+        if self.is_horizontal() {
+            (x1.min(x2), x1.max(x2), y1)
+        } else {
+            (y1.min(y2), y1.max(y2), x1)
+        }
+    }
+
+    const fn steps_to(&self, pos: Position) -> i64 {
+        self.steps + (pos.0 - self.start.0).abs() + (pos.1 - self.start.1).abs()
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Wire(Vec<Segment>);
+
+impl Wire {
+    // Corners of the wire, starting at the origin.
+    fn points(&self) -> Vec<Position> {
+        std::iter::once(Position(0, 0))
+            .chain(self.0.iter().map(|it| it.end))
+            .collect()
+    }
+}
+
+fn get_segments(wire: &str) -> Wire {
+    let mut pos = Position(0, 0);
+    let mut steps = 0;
+    let mut segments = Vec::new();
+
+    for section in wire
+        .split(',')
+        .filter_map(|it| it.trim().parse::<CableSection>().ok())
+    {
+        let (dx, dy, n) = match section {
+            CableSection::Right(n) => (1, 0, n),
+            CableSection::Down(n) => (0, -1, n),
+            CableSection::Left(n) => (-1, 0, n),
+            CableSection::Up(n) => (0, 1, n),
+        };
+
+        let end = Position(pos.0 + dx * n, pos.1 + dy * n);
+        if n != 0 {
+            segments.push(Segment {
+                start: pos,
+                end,
+                steps,
+            });
+        }
+
+        pos = end;
+        steps += n.abs();
+    }
+
+    Wire(segments)
 }
 
+// One wire per non-empty line.
 #[aoc_generator(day3)]
-pub fn generate(inp: &str) -> [Vec<Position>; 2] {
-    let lines = inp.split('\n').collect_vec();
+pub fn generate(inp: &str) -> Vec<Wire> {
+    inp.lines()
+        .filter(|it| !it.trim().is_empty())
+        .map(get_segments)
+        .collect()
+}
+
+// At the same x, horizontal segments are added before the vertical ones are checked against
+// them and removed afterwards, so crossings at end points are found as well.
+#[derive(Copy, Clone, PartialOrd, Ord, PartialEq, Eq, Debug)]
+enum Event {
+    Insert,
+    Query,
+    Remove,
+}
+
+type Visit = (usize, i64);
+
+// Every point of an overlap is a crossing. Reports only its ends and the point closest to the
+// origin: the combined steps change linearly along the overlap, so both metrics have their
+// minimum at one of those. Their neighbours are reported too, in case one of them is the
+// origin itself, which doesn't count.
+fn overlap<F>(lhs: (usize, &Segment), rhs: (usize, &Segment), found: &mut F)
+where
+    F: FnMut(Position, Visit, Visit),
+{
+    let ((lwire, lseg), (rwire, rseg)) = (lhs, rhs);
+    let (llo, lhi, line) = lseg.span();
+    let (rlo, rhi, _) = rseg.span();
+    let (lo, hi) = (llo.max(rlo), lhi.min(rhi));
+    if lo > hi {
+        return;
+    }
 
-    let first_wire = lines[0];
-    let second_wire = lines[1];
+    let candidates = [lo, hi, 0.clamp(lo, hi)]
+        .iter()
+        .flat_map(|&it| it - 1..=it + 1)
+        .filter(|it| (lo..=hi).contains(it))
+        .sorted()
+        .dedup();
+
+    for along in candidates {
+        let pos = if lseg.is_horizontal() {
+            Position(along, line)
+        } else {
+            Position(line, along)
+        };
 
-    [get_coords(first_wire), get_coords(second_wire)]
+        found(
+            pos,
+            (lwire, lseg.steps_to(pos)),
+            (rwire, rseg.steps_to(pos)),
+        );
+    }
 }
 
-fn get_intersections(lhs: &[Position], rhs: &[Position]) -> Vec<Position> {
-    let lhs_set = lhs.iter().collect::<HashSet<_>>();
-    let rhs_set = rhs.iter().collect::<HashSet<_>>();
-    lhs_set.intersection(&rhs_set).map(|it| **it).collect()
+// Sweeps a vertical line from left to right over all segments, keeping the horizontal ones it
+// currently crosses ordered by y. Calls `found` with the wire and its steps for both wires of
+// every crossing between different wires.
+fn sweep<'a, I, F>(wires: I, mut found: F)
+where
+    I: IntoIterator<Item = (usize, &'a Wire)>,
+    F: FnMut(Position, Visit, Visit),
+{
+    let mut events = Vec::new();
+    for (wire, it) in wires {
+        for segment in &it.0 {
+            let (lo, hi, _) = segment.span();
+
+            if segment.is_horizontal() {
+                events.push((lo, Event::Insert, wire, segment));
+                events.push((hi, Event::Remove, wire, segment));
+            } else {
+                events.push((segment.start.0, Event::Query, wire, segment));
+            }
+        }
+    }
+    events.sort_by_key(|&(x, event, ..)| (x, event));
+
+    let mut active: BTreeMap<i64, Vec<(usize, &Segment)>> = BTreeMap::new();
+    let mut verticals: Vec<(usize, &Segment)> = Vec::new();
+
+    for (x, event, wire, segment) in events {
+        match event {
+            Event::Insert => {
+                let line = active.entry(segment.start.1).or_default();
+                for &other in line.iter().filter(|it| it.0 != wire) {
+                    overlap((wire, segment), other, &mut found);
+                }
+                line.push((wire, segment));
+            }
+            Event::Query => {
+                if verticals.first().is_some_and(|it| it.1.start.0 != x) {
+                    verticals.clear();
+                }
+                for &other in verticals.iter().filter(|it| it.0 != wire) {
+                    overlap((wire, segment), other, &mut found);
+                }
+                verticals.push((wire, segment));
+
+                let (lo, hi, _) = segment.span();
+                for (&y, line) in active.range(lo..=hi) {
+                    let pos = Position(x, y);
+
+                    for &(other, horizontal) in line.iter().filter(|it| it.0 != wire) {
+                        found(
+                            pos,
+                            (wire, segment.steps_to(pos)),
+                            (other, horizontal.steps_to(pos)),
+                        );
+                    }
+                }
+            }
+            Event::Remove => {
+                if let Some(line) = active.get_mut(&segment.start.1) {
+                    line.retain(|it| it.0 != wire || !std::ptr::eq(it.1, segment));
+                    if line.is_empty() {
+                        active.remove(&segment.start.1);
+                    }
+                }
+            }
+        }
+    }
 }
 
-#[aoc(day3, part1)]
-pub fn part1(wires: &[Vec<Position>; 2]) -> Option<i64> {
-    let [first_wire, second_wire] = wires;
-    let intersections = get_intersections(first_wire, second_wire);
-    intersections.iter().map(|it| it.0.abs() + it.1.abs()).min()
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct Crossing {
+    pub pos: Position,
+    // The wires meeting here, with the fewest steps each of them needs to get here.
+    pub wires: BTreeMap<usize, i64>,
 }
 
-#[aoc(day3, part2)]
-pub fn part2(wires: &[Vec<Position>; 2]) -> Option<i64> {
-    fn pos(v: &[Position], e: &Position) -> Option<i64> {
-        #[allow(clippy::cast_possible_wrap)]
-        v.iter().position(|it| *it == *e).map(|it| it as i64 + 1)
+impl Crossing {
+    #[must_use]
+    pub const fn distance(&self) -> i64 {
+        self.pos.distance()
     }
 
-    let [first_wire, second_wire] = wires;
-    let intersections = get_intersections(first_wire, second_wire);
+    #[must_use]
+    pub fn steps(&self) -> i64 {
+        self.wires.values().sum()
+    }
+}
 
-    intersections
-        .iter()
-        .filter_map(|it| {
-            let lpos = pos(first_wire, it);
-            let rpos = pos(second_wire, it);
-            lpos.and_then(|l| rpos.map(|r| l + r))
-        })
-        .min()
+fn collect_crossings<'a, I>(wires: I) -> Vec<Crossing>
+where
+    I: IntoIterator<Item = (usize, &'a Wire)>,
+{
+    let mut crossings: BTreeMap<Position, BTreeMap<usize, i64>> = BTreeMap::new();
+
+    sweep(wires, |pos, lhs, rhs| {
+        if pos == Position(0, 0) {
+            return;
+        }
+
+        let meeting = crossings.entry(pos).or_default();
+        for (wire, steps) in [lhs, rhs] {
+            let fewest = meeting.entry(wire).or_insert(steps);
+            *fewest = (*fewest).min(steps);
+        }
+    });
+
+    crossings
+        .into_iter()
+        .map(|(pos, wires)| Crossing { pos, wires })
+        .collect()
+}
+
+// Every point other than the origin where at least two wires meet, ordered by position.
+// Where wires run on top of each other only the points `overlap` reports are included.
+#[must_use]
+pub fn crossings(wires: &[Wire]) -> Vec<Crossing> {
+    collect_crossings(wires.iter().enumerate())
+}
+
+// Crossings of the wires at `lhs` and `rhs` only, `None` if either index is out of range.
+#[must_use]
+pub fn pairwise(wires: &[Wire], lhs: usize, rhs: usize) -> Option<Vec<Crossing>> {
+    Some(collect_crossings([
+        (lhs, wires.get(lhs)?),
+        (rhs, wires.get(rhs)?),
+    ]))
+}
+
+// Points where all wires meet, none for fewer than two wires.
+#[must_use]
+pub fn common(wires: &[Wire]) -> Vec<Crossing> {
+    if wires.len() < 2 {
+        return Vec::new();
+    }
+
+    crossings(wires)
+        .into_iter()
+        .filter(|it| it.wires.len() == wires.len())
+        .collect()
+}
+
+// Draws every wire as a polyline in its own colour and marks the crossings, with rings around
+// the closest one by distance (red) and by steps (blue). The y axis points up as in the
+// puzzle, SVG's points down, so y is negated.
+#[must_use]
+pub fn to_svg(wires: &[Wire]) -> String {
+    let crossings = crossings(wires);
+    let wires = wires.iter().map(Wire::points).collect_vec();
+
+    let corners = || wires.iter().flatten();
+    let (min_x, max_x) = corners()
+        .map(|it| it.0)
+        .minmax()
+        .into_option()
+        .unwrap_or((0, 0));
+    let (min_y, max_y) = corners()
+        .map(|it| it.1)
+        .minmax()
+        .into_option()
+        .unwrap_or((0, 0));
+    let size = (max_x - min_x).max(max_y - min_y).max(1);
+    let margin = size / 20 + 1;
+    #[allow(clippy::cast_precision_loss)]
+    let radius = size as f64 / 80.0;
+
+    let mut svg = String::new();
+    let _ = writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="{} {} {} {}">"#,
+        min_x - margin,
+        -max_y - margin,
+        max_x - min_x + 2 * margin,
+        max_y - min_y + 2 * margin
+    );
+
+    for (idx, points) in wires.iter().enumerate() {
+        // Golden angle steps keep the hues apart for any number of wires.
+        let _ = writeln!(
+            svg,
+            r#"  <polyline points="{}" fill="none" stroke="hsl({}, 70%, 45%)" stroke-width="2" vector-effect="non-scaling-stroke"><title>wire {}</title></polyline>"#,
+            points
+                .iter()
+                .map(|it| format!("{},{}", it.0, -it.1))
+                .join(" "),
+            idx * 137 % 360,
+            idx
+        );
+    }
+
+    let _ = writeln!(
+        svg,
+        r#"  <rect x="{}" y="{}" width="{}" height="{}"><title>origin</title></rect>"#,
+        -radius,
+        -radius,
+        2.0 * radius,
+        2.0 * radius
+    );
+
+    for crossing in &crossings {
+        let _ = writeln!(
+            svg,
+            r#"  <circle cx="{}" cy="{}" r="{}"><title>{}, {}: wires {}, distance {}, steps {}</title></circle>"#,
+            crossing.pos.0,
+            -crossing.pos.1,
+            radius,
+            crossing.pos.0,
+            crossing.pos.1,
+            crossing.wires.keys().join(", "),
+            crossing.distance(),
+            crossing.steps()
+        );
+    }
+
+    let closest = [
+        (crossings.iter().min_by_key(|it| it.distance()), "red"),
+        (crossings.iter().min_by_key(|it| it.steps()), "blue"),
+    ];
+    for (crossing, colour) in &closest {
+        if let Some(crossing) = crossing {
+            let _ = writeln!(
+                svg,
+                r#"  <circle cx="{}" cy="{}" r="{}" fill="none" stroke="{}" stroke-width="2" vector-effect="non-scaling-stroke"/>"#,
+                crossing.pos.0,
+                -crossing.pos.1,
+                4.0 * radius,
+                colour
+            );
+        }
+    }
+
+    svg.push_str("</svg>\n");
+    svg
+}
+
+#[aoc(day3, part1)]
+pub fn part1(wires: &[Wire]) -> Option<i64> {
+    common(wires).iter().map(Crossing::distance).min()
+}
+
+#[aoc(day3, part2)]
+pub fn part2(wires: &[Wire]) -> Option<i64> {
+    common(wires).iter().map(Crossing::steps).min()
 }
 
 #[cfg(test)]
@@ -136,4 +449,65 @@ mod tests {
             assert_eq!(res, Some(410));
         }
     }
+
+    #[test]
+    fn test_overlaps() {
+        // The second wire runs along the first one from (3, 0) to (7, 0).
+        let data = generate("R10,U2\nU1,R3,D1,R4,U3");
+        assert_eq!(part1(&data), Some(3));
+        assert_eq!(part2(&data), Some(8));
+
+        // The same, turned by 90 degrees.
+        let data = generate("U10,R2\nR1,U3,L1,U4,R3");
+        assert_eq!(part1(&data), Some(3));
+        assert_eq!(part2(&data), Some(8));
+
+        // Overlapping through the origin, which isn't a crossing.
+        let data = generate("R4,L6\nL2");
+        assert_eq!(part1(&data), Some(1));
+        assert_eq!(part2(&data), Some(10));
+    }
+
+    #[test]
+    fn test_many_wires() {
+        let data = generate("R10\nU1,R5,D2\nU2,R3,D4\n");
+
+        let found = crossings(&data)
+            .iter()
+            .map(|it| (it.pos, it.wires.keys().copied().collect_vec(), it.steps()))
+            .collect_vec();
+        assert_eq!(
+            found,
+            vec![
+                (Position(0, 1), vec![1, 2], 2),
+                (Position(3, 0), vec![0, 2], 10),
+                (Position(3, 1), vec![1, 2], 10),
+                (Position(5, 0), vec![0, 1], 12),
+            ]
+        );
+        assert_eq!(pairwise(&data, 2, 0).map(|it| it.len()), Some(1));
+        assert_eq!(pairwise(&data, 0, 3), None);
+        assert!(common(&data).is_empty());
+
+        // The third wire runs along the second one, all three meet at (5, 0).
+        let data = generate("R10\nU1,R5,D2\nD1,R5,U2");
+        let all = common(&data);
+        assert_eq!(all.len(), 1);
+        assert_eq!((all[0].distance(), all[0].steps()), (5, 19));
+
+        assert_eq!(part1(&generate("R8")), None);
+        assert_eq!(part2(&generate("")), None);
+    }
+
+    #[test]
+    fn test_svg() {
+        let svg = to_svg(&generate("R8,U5,L5,D3\nU7,R6,D4,L4"));
+
+        assert!(svg.starts_with(r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="-1 -8 10 9">"#));
+        assert_eq!(svg.matches("<polyline").count(), 2);
+        assert!(svg.contains(r#"points="0,0 8,0 8,-5 3,-5 3,-2""#));
+        assert!(svg.contains("<title>3, 3: wires 0, 1, distance 6, steps 40</title>"));
+        assert!(svg.contains(r#"<circle cx="3" cy="-3" r="0.4" fill="none" stroke="red""#));
+        assert!(svg.contains(r#"<circle cx="6" cy="-5" r="0.4" fill="none" stroke="blue""#));
+    }
 }
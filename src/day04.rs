@@ -1,64 +1,374 @@
-use std::iter::Filter;
-use std::ops::Range;
+use std::collections::HashMap;
+use std::fmt;
 
 use aoc_runner_derive::{aoc, aoc_generator};
+use itertools::Itertools;
 use parse_display::{Display as PDisplay, FromStr as PFromStr};
 
 #[derive(PDisplay, PFromStr)]
 #[display("{0}-{1}")]
-pub struct InputRange(i64, i64);
+pub struct InputRange(u128, u128);
 
-impl InputRange {
-    pub fn filter_valid(&self) -> Filter<Range<i64>, fn(&i64) -> bool> {
-        (self.0..self.1).filter(|it| is_valid(&it.to_string()))
+#[derive(PDisplay, PFromStr, Copy, Clone, PartialEq, Eq, Debug)]
+#[display(style = "kebab-case")]
+pub enum Order {
+    NonDecreasing,
+    Increasing,
+    NonIncreasing,
+    Decreasing,
+}
+
+impl Order {
+    const fn allows(self, prev: u8, next: u8) -> bool {
+        match self {
+            Self::NonDecreasing => prev <= next,
+            Self::Increasing => prev < next,
+            Self::NonIncreasing => prev >= next,
+            Self::Decreasing => prev > next,
+        }
+    }
+}
+
+// A run is a maximal group of equal adjacent digits. Leading zeros are not part of a password.
+#[derive(PDisplay, PFromStr, Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Rule {
+    #[display("digits {0}")]
+    Digits(Order),
+
+    #[display("run at least {0}")]
+    RunAtLeast(u8),
+
+    #[display("run exactly {0}")]
+    RunExactly(u8),
+
+    #[display("forbid {0}")]
+    Forbid(u8),
+
+    #[display("length {0}")]
+    Length(u32),
+}
+
+impl Rule {
+    fn is_supported(self) -> bool {
+        match self {
+            Self::Digits(_) => true,
+            Self::RunAtLeast(n) | Self::RunExactly(n) => (1..=64).contains(&n),
+            Self::Forbid(digit) => digit <= 9,
+            Self::Length(len) => len >= 1,
+        }
     }
+}
 
-    pub fn filter_valid_p2(&self) -> Filter<Range<i64>, fn(&i64) -> bool> {
-        (self.0..self.1).filter(|it| is_valid(&it.to_string()) && one_double_pair(&it.to_string()))
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct RuleError {
+    pub line: usize,
+    pub text: String,
+}
+
+impl fmt::Display for RuleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid rule at line {}: {:?}", self.line, self.text)
     }
 }
 
-#[aoc_generator(day4)]
-pub fn generate(inp: &str) -> Option<InputRange> {
-    inp.parse().ok()
+impl std::error::Error for RuleError {}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct RuleSet {
+    rules: Vec<Rule>,
+    orders: Vec<Order>,
+    forbidden: [bool; 10],
+    // (exactly, length) for every distinct run rule, at most 128 of them.
+    runs: Vec<(bool, u8)>,
+    // Runs longer than every run rule cares about are all stored as this length.
+    max_run: u8,
 }
 
-fn increasing(num: &[char]) -> bool {
-    num.windows(2).all(|it| it[0] <= it[1])
+// What the rules need to know about the digits of a password so far.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+struct Prefix {
+    last: u8,
+    run: u8,
+    // Run rules satisfied by a run that already ended, one bit each.
+    satisfied: u128,
 }
 
-fn two_adjacent_eq(num: &[char]) -> bool {
-    num.windows(2).any(|it| it[0] == it[1])
+// A prefix while counting, which may not have reached its first non-zero digit yet.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+enum Digits {
+    Zeros,
+    Started(Prefix),
+}
+
+impl RuleSet {
+    /// # Errors
+    ///
+    /// Fails on rules `RuleSet::parse` would reject, e.g. `Forbid(10)`. The error's `line` is
+    /// the position of the first such rule in `rules`, counting from 1.
+    pub fn new(rules: Vec<Rule>) -> Result<Self, RuleError> {
+        if let Some((idx, rule)) = rules.iter().find_position(|it| !it.is_supported()) {
+            return Err(RuleError {
+                line: idx + 1,
+                text: rule.to_string(),
+            });
+        }
+
+        let runs = rules
+            .iter()
+            .filter_map(|it| match *it {
+                Rule::RunAtLeast(n) => Some((false, n)),
+                Rule::RunExactly(n) => Some((true, n)),
+                _ => None,
+            })
+            .unique()
+            .collect::<Vec<_>>();
+
+        let mut forbidden = [false; 10];
+        for rule in &rules {
+            if let Rule::Forbid(digit) = *rule {
+                forbidden[usize::from(digit)] = true;
+            }
+        }
+
+        Ok(Self {
+            orders: rules
+                .iter()
+                .filter_map(|it| match *it {
+                    Rule::Digits(order) => Some(order),
+                    _ => None,
+                })
+                .collect(),
+            forbidden,
+            max_run: runs.iter().map(|it| it.1 + 1).max().unwrap_or(1),
+            runs,
+            rules,
+        })
+    }
+
+    /// One rule per line, e.g. `digits non-decreasing`, `run at least 2`, `run exactly 2`,
+    /// `forbid 7` or `length 6`. Everything after a `#` is a comment.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `RuleError` for the first line that isn't a supported rule.
+    pub fn parse(inp: &str) -> Result<Self, RuleError> {
+        let rules = inp
+            .lines()
+            .enumerate()
+            .map(|(idx, raw)| (idx, raw, raw.split('#').next().unwrap_or_default().trim()))
+            .filter(|(_, _, line)| !line.is_empty())
+            .map(|(idx, raw, line)| {
+                line.parse::<Rule>()
+                    .ok()
+                    .filter(|it| it.is_supported())
+                    .ok_or_else(|| RuleError {
+                        line: idx + 1,
+                        text: raw.to_owned(),
+                    })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Self::new(rules)
+    }
+
+    #[must_use]
+    pub fn rules(&self) -> &[Rule] {
+        &self.rules
+    }
+
+    fn start(&self, digit: u8) -> Option<Prefix> {
+        if self.forbidden[usize::from(digit)] {
+            return None;
+        }
+
+        Some(Prefix {
+            last: digit,
+            run: 1,
+            satisfied: 0,
+        })
+    }
+
+    // `None` if the digit breaks one of the rules.
+    fn push(&self, prefix: Prefix, digit: u8) -> Option<Prefix> {
+        if self.forbidden[usize::from(digit)]
+            || !self.orders.iter().all(|it| it.allows(prefix.last, digit))
+        {
+            return None;
+        }
+
+        if digit == prefix.last {
+            return Some(Prefix {
+                run: (prefix.run + 1).min(self.max_run),
+                ..prefix
+            });
+        }
+
+        Some(Prefix {
+            last: digit,
+            run: 1,
+            satisfied: prefix.satisfied | self.satisfied_by(prefix.run),
+        })
+    }
+
+    fn satisfied_by(&self, run: u8) -> u128 {
+        self.runs
+            .iter()
+            .enumerate()
+            .filter(|(_, &(exactly, n))| if exactly { run == n } else { run >= n })
+            .fold(0, |acc, (idx, _)| acc | 1 << idx)
+    }
+
+    fn accepts(&self, prefix: Prefix) -> bool {
+        let all = u128::MAX
+            .checked_shr(128 - self.runs.len() as u32)
+            .unwrap_or(0);
+        (prefix.satisfied | self.satisfied_by(prefix.run)) == all
+    }
+
+    // `None` if the digit breaks one of the rules.
+    fn extend(&self, digits: Digits, digit: u8) -> Option<Digits> {
+        match digits {
+            Digits::Zeros if digit == 0 => Some(Digits::Zeros),
+            Digits::Zeros => self.start(digit).map(Digits::Started),
+            Digits::Started(prefix) => self.push(prefix, digit).map(Digits::Started),
+        }
+    }
+
+    // Checks every rule but `Length`, which `InputRange` handles by narrowing the range.
+    fn matches_digits(&self, num: u128) -> bool {
+        let mut digits = num.to_string().into_bytes().into_iter().map(|it| it - b'0');
+
+        let first = digits.next().and_then(|it| self.start(it));
+        first
+            .and_then(|first| digits.try_fold(first, |acc, it| self.push(acc, it)))
+            .is_some_and(|it| self.accepts(it))
+    }
+
+    #[must_use]
+    pub fn is_valid(&self, num: u128) -> bool {
+        let len = num.to_string().len();
+        self.lengths().all(|it| it as usize == len) && self.matches_digits(num)
+    }
+
+    fn lengths(&self) -> impl Iterator<Item = u32> + '_ {
+        self.rules.iter().filter_map(|it| match *it {
+            Rule::Length(len) => Some(len),
+            _ => None,
+        })
+    }
+
+    // Counts the valid passwords in `0..n` digit by digit. Prefixes that are already below the
+    // same number of leading digits of `n` can be continued with any digit, so they are only
+    // kept as a count per `Digits`.
+    fn count_below(&self, n: u128) -> u128 {
+        let mut below: HashMap<Digits, u128> = HashMap::new();
+        let mut tight = Some(Digits::Zeros);
+
+        for limit in n.to_string().bytes().map(|it| it - b'0') {
+            let mut next = HashMap::new();
+
+            for (digits, count) in below {
+                for digit in 0..=9 {
+                    if let Some(digits) = self.extend(digits, digit) {
+                        *next.entry(digits).or_default() += count;
+                    }
+                }
+            }
+
+            if let Some(digits) = tight {
+                for digit in 0..limit {
+                    if let Some(digits) = self.extend(digits, digit) {
+                        *next.entry(digits).or_default() += 1;
+                    }
+                }
+                tight = self.extend(digits, limit);
+            }
+
+            below = next;
+        }
+
+        below
+            .into_iter()
+            .filter(|(digits, _)| match digits {
+                Digits::Started(prefix) => self.accepts(*prefix),
+                Digits::Zeros => self.matches_digits(0),
+            })
+            .map(|(_, count)| count)
+            .sum()
+    }
 }
 
-fn one_double_pair(num: &str) -> bool {
-    num.chars()
-        .map(|it| num.chars().filter(|e| *e == it).count())
-        .any(|it| it == 2)
+impl fmt::Display for RuleSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.rules.iter().join("\n"))
+    }
 }
 
-fn is_valid(num: &str) -> bool {
-    let chrs = num.chars().collect::<Vec<char>>();
+impl InputRange {
+    // The range limited to numbers with as many digits as every `Length` rule asks for.
+    fn narrow(&self, rules: &RuleSet) -> (u128, u128) {
+        rules.lengths().fold((self.0, self.1), |(lo, hi), len| {
+            let min = if len == 1 {
+                Some(0)
+            } else {
+                10u128.checked_pow(len - 1)
+            };
+            let max = 10u128.checked_pow(len).unwrap_or(u128::MAX);
+            (lo.max(min.unwrap_or(hi)), hi.min(max))
+        })
+    }
 
-    if !increasing(&chrs) {
-        return false;
+    pub fn filter<'a>(&self, rules: &'a RuleSet) -> impl Iterator<Item = u128> + 'a {
+        let (lo, hi) = self.narrow(rules);
+        (lo..hi).filter(move |&it| rules.matches_digits(it))
     }
 
-    if !two_adjacent_eq(&chrs) {
-        return false;
+    // Same as `filter(rules).count()`, without enumerating the range.
+    #[must_use]
+    pub fn count(&self, rules: &RuleSet) -> u128 {
+        let (lo, hi) = self.narrow(rules);
+        if hi <= lo {
+            return 0;
+        }
+
+        rules.count_below(hi) - rules.count_below(lo)
     }
+}
+
+#[aoc_generator(day4)]
+#[must_use]
+pub fn generate(inp: &str) -> Option<InputRange> {
+    inp.parse().ok()
+}
 
-    true
+fn part1_rules() -> Result<RuleSet, RuleError> {
+    RuleSet::new(vec![
+        Rule::Digits(Order::NonDecreasing),
+        Rule::RunAtLeast(2),
+    ])
 }
 
+fn part2_rules() -> Result<RuleSet, RuleError> {
+    RuleSet::new(vec![
+        Rule::Digits(Order::NonDecreasing),
+        Rule::RunExactly(2),
+    ])
+}
+
+/// # Errors
+///
+/// Returns a `RuleError` if `RuleSet::new` rejects the puzzle's rules.
 #[aoc(day4, part1)]
-pub fn part1(r: &InputRange) -> usize {
-    r.filter_valid().count()
+pub fn part1(r: &InputRange) -> Result<u128, RuleError> {
+    Ok(r.count(&part1_rules()?))
 }
 
+/// # Errors
+///
+/// Like `part1`.
 #[aoc(day4, part2)]
-pub fn part2(r: &InputRange) -> usize {
-    r.filter_valid_p2().count()
+pub fn part2(r: &InputRange) -> Result<u128, RuleError> {
+    Ok(r.count(&part2_rules()?))
 }
 
 #[cfg(test)]
@@ -69,15 +379,15 @@ mod tests {
     fn test_samples_p1() {
         {
             let range = InputRange(111_111, 111_112);
-            assert_eq!(1, part1(&range));
+            assert_eq!(Ok(1), part1(&range));
         }
         {
             let range = InputRange(223_450, 223_451);
-            assert_eq!(0, part1(&range));
+            assert_eq!(Ok(0), part1(&range));
         }
         {
             let range = InputRange(123_789, 123_790);
-            assert_eq!(0, part1(&range));
+            assert_eq!(Ok(0), part1(&range));
         }
     }
 
@@ -85,15 +395,92 @@ mod tests {
     fn test_samples_p2() {
         {
             let range = InputRange(112_233, 112_234);
-            assert_eq!(1, part2(&range));
+            assert_eq!(Ok(1), part2(&range));
         }
         {
             let range = InputRange(123_444, 123_445);
-            assert_eq!(0, part2(&range));
+            assert_eq!(Ok(0), part2(&range));
         }
         {
             let range = InputRange(111_122, 111_123);
-            assert_eq!(1, part2(&range));
+            assert_eq!(Ok(1), part2(&range));
         }
     }
+
+    // The rules as they were before they became configurable.
+    fn reference(num: u128, exact_double: bool) -> bool {
+        let num = num.to_string();
+        let chrs = num.chars().collect::<Vec<char>>();
+        let double = num
+            .chars()
+            .map(|it| num.chars().filter(|e| *e == it).count())
+            .any(|it| it == 2);
+
+        chrs.windows(2).all(|it| it[0] <= it[1])
+            && chrs.windows(2).any(|it| it[0] == it[1])
+            && (double || !exact_double)
+    }
+
+    #[test]
+    fn test_count() {
+        let pair_rules = part1_rules().expect("supported rules");
+        let double_rules = part2_rules().expect("supported rules");
+
+        let range = InputRange(99_000, 400_000);
+        assert_eq!(
+            range.count(&pair_rules),
+            (99_000..400_000).filter(|&it| reference(it, false)).count() as u128
+        );
+        assert_eq!(
+            range.count(&double_rules),
+            (99_000..400_000).filter(|&it| reference(it, true)).count() as u128
+        );
+
+        // 20 digit numbers with non-decreasing digits are multisets of 20 digits from 1 to 9,
+        // and all of them have a pair.
+        let range = InputRange(10u128.pow(19), 10u128.pow(20));
+        assert_eq!(range.count(&pair_rules), 3_108_105);
+
+        assert_eq!(InputRange(5, 5).count(&pair_rules), 0);
+        assert_eq!(InputRange(0, 23).count(&double_rules), 2);
+    }
+
+    #[test]
+    fn test_rules() {
+        let rules = RuleSet::parse(
+            "# comment\ndigits non-increasing\nrun at least 3\nrun exactly 1  # a single digit\nforbid 0\nforbid 5\nlength 5\n",
+        )
+        .expect("valid rules");
+        assert_eq!(rules.rules()[0], Rule::Digits(Order::NonIncreasing));
+        assert_eq!(RuleSet::parse(&rules.to_string()), Ok(rules.clone()));
+
+        assert!(rules.is_valid(99_921));
+        assert!(!rules.is_valid(99_920));
+        assert!(!rules.is_valid(9_992));
+        assert!(!rules.is_valid(99_211));
+
+        let range = InputRange(0, 1_000_000);
+        assert_eq!(range.count(&rules), range.filter(&rules).count() as u128);
+        assert_eq!(
+            range.count(&rules),
+            (0..1_000_000).filter(|&it| rules.is_valid(it)).count() as u128
+        );
+
+        assert_eq!(
+            RuleSet::parse("digits increasing\nrun exactly 0"),
+            Err(RuleError {
+                line: 2,
+                text: String::from("run exactly 0")
+            })
+        );
+        assert!(RuleSet::parse("forbid 10").is_err());
+
+        assert_eq!(
+            RuleSet::new(vec![Rule::Length(6), Rule::Forbid(10), Rule::RunAtLeast(0)]),
+            Err(RuleError {
+                line: 2,
+                text: String::from("forbid 10")
+            })
+        );
+    }
 }
@@ -1,30 +1,19 @@
 use aoc_runner_derive::{aoc, aoc_generator};
 
-use crate::intcode::{IntCode, State};
+use crate::intcode::diagnostics::{run_diagnostics, Report};
+use crate::intcode::program::{ParseError, Program};
 
 #[aoc_generator(day5)]
-pub fn generate(inp: &str) -> Vec<i64> {
-    inp.split(',').filter_map(|it| it.parse().ok()).collect()
-}
-
-fn run_vm_with_input(v: &[i64], inp: i64) -> i64 {
-    let mut vm = IntCode::new(v);
-
-    loop {
-        match vm.run() {
-            State::Write(0) => {}
-            State::Write(n) | State::Halted(n) => return n,
-            State::Waiting => vm.input(inp),
-        }
-    }
+pub fn generate(inp: &str) -> Result<Program, ParseError> {
+    Program::parse(inp)
 }
 
 #[aoc(day5, part1)]
-pub fn part1(v: &[i64]) -> i64 {
-    run_vm_with_input(v, 1)
+pub fn part1(v: &[i64]) -> Result<i64, Report> {
+    run_diagnostics(v, 1).into_code()
 }
 
 #[aoc(day5, part2)]
-pub fn part2(v: &[i64]) -> i64 {
-    run_vm_with_input(v, 5)
+pub fn part2(v: &[i64]) -> Result<i64, Report> {
+    run_diagnostics(v, 5).into_code()
 }
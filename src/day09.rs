@@ -1,30 +1,19 @@
 use aoc_runner_derive::{aoc, aoc_generator};
 
-use crate::intcode::{IntCode, State};
+use crate::intcode::diagnostics::{run_diagnostics, Report};
+use crate::intcode::program::{ParseError, Program};
 
 #[aoc_generator(day9)]
-pub fn generate(inp: &str) -> Vec<i64> {
-    inp.split(',').filter_map(|it| it.parse().ok()).collect()
-}
-
-fn run_vm(ram: &[i64], inp: i64) -> i64 {
-    let mut vm = IntCode::new(ram);
-
-    loop {
-        match vm.run() {
-            State::Waiting => vm.input(inp),
-            State::Write(n) => return n,
-            State::Halted(_) => {}
-        }
-    }
+pub fn generate(inp: &str) -> Result<Program, ParseError> {
+    Program::parse(inp)
 }
 
 #[aoc(day9, part1)]
-pub fn part1(v: &[i64]) -> i64 {
-    run_vm(v, 1)
+pub fn part1(v: &[i64]) -> Result<i64, Report> {
+    run_diagnostics(v, 1).into_code()
 }
 
 #[aoc(day9, part2)]
-pub fn part2(v: &[i64]) -> i64 {
-    run_vm(v, 2)
+pub fn part2(v: &[i64]) -> Result<i64, Report> {
+    run_diagnostics(v, 2).into_code()
 }
@@ -4,15 +4,18 @@ use std::ops::{Add, AddAssign};
 use aoc_runner_derive::{aoc, aoc_generator};
 use itertools::Itertools;
 
-use crate::intcode::{IntCode, State};
+use crate::intcode::device::{drive, Device};
+use crate::intcode::framing::FrameError;
+use crate::intcode::program::{ParseError, Program};
+use crate::intcode::IntCode;
 
 #[derive(Copy, Clone, PartialEq, Eq)]
-enum Color {
+pub enum Color {
     Black,
     White,
 }
 
-#[derive(PartialOrd, Ord, Eq, PartialEq, Hash, Copy, Clone)]
+#[derive(PartialOrd, Ord, Eq, PartialEq, Hash, Copy, Clone, Debug)]
 struct MapPosition(i64, i64);
 
 impl Add for MapPosition {
@@ -30,72 +33,102 @@ impl AddAssign for MapPosition {
     }
 }
 
+/// # Errors
+///
+/// Returns a `ParseError` if the input isn't a comma separated Intcode program.
 #[aoc_generator(day11)]
-pub fn generate(inp: &str) -> Vec<i64> {
-    inp.split(',').filter_map(|it| it.parse().ok()).collect()
+pub fn generate(inp: &str) -> Result<Program, ParseError> {
+    Program::parse(inp)
 }
 
-fn run_robot(v: &[i64], c: Color) -> HashMap<MapPosition, Color> {
-    let mut map: HashMap<MapPosition, Color> = HashMap::new();
-    let mut vm = IntCode::new(v);
-
-    let mut robot_pos = MapPosition(0, 0);
-    let mut cur_dir = MapPosition(0, 1);
-
-    map.insert(MapPosition(0, 0), c);
-
-    loop {
-        match vm.run() {
-            State::Waiting => match map.get(&robot_pos) {
-                Some(col) if *col == Color::White => vm.input(1),
-                _ => vm.input(0),
-            },
-            State::Write(n) => {
-                let new_col = if n == 0 { Color::Black } else { Color::White };
-                map.entry(robot_pos)
-                    .and_modify(|it| *it = new_col)
-                    .or_insert(new_col);
-
-                if let State::Write(n) = vm.run() {
-                    // n = 0 => turn left
-                    // n = 1 => turn right
-                    match cur_dir {
-                        MapPosition(0, 1) => {
-                            cur_dir = MapPosition(if n == 0 { -1 } else { 1 }, 0);
-                        }
-                        MapPosition(1, 0) => {
-                            cur_dir = MapPosition(0, if n == 0 { 1 } else { -1 });
-                        }
-                        MapPosition(0, -1) => {
-                            cur_dir = MapPosition(if n == 0 { 1 } else { -1 }, 0);
-                        }
-                        MapPosition(-1, 0) => {
-                            cur_dir = MapPosition(0, if n == 0 { -1 } else { 1 });
-                        }
-                        _ => unreachable!("Invalid direction!"),
-                    }
-
-                    robot_pos += cur_dir;
-                } else {
-                    unreachable!("Didn't output two writes!");
-                }
-            }
-            State::Halted(_) => {
-                return map;
-            }
+// Paints the hull square it stands on and turns with every pair of outputs, and reports
+// the color below it whenever the VM asks.
+pub struct HullRobot {
+    map: HashMap<MapPosition, Color>,
+    pos: MapPosition,
+    dir: MapPosition,
+}
+
+impl HullRobot {
+    #[must_use]
+    pub fn new(start: Color) -> Self {
+        let mut map = HashMap::new();
+        map.insert(MapPosition(0, 0), start);
+
+        Self {
+            map,
+            pos: MapPosition(0, 0),
+            dir: MapPosition(0, 1),
+        }
+    }
+
+    // Every panel painted at least once, plus the starting one.
+    #[must_use]
+    pub fn painted(&self) -> usize {
+        self.map.len()
+    }
+}
+
+impl Device<2> for HullRobot {
+    type Frame = [i64; 2];
+    type Error = FrameError;
+
+    fn on_output(&mut self, [color, turn]: [i64; 2]) -> Result<(), Self::Error> {
+        let new_col = if color == 0 {
+            Color::Black
+        } else {
+            Color::White
+        };
+        self.map.insert(self.pos, new_col);
+
+        // turn = 0 => turn left
+        // turn = 1 => turn right
+        self.dir = match self.dir {
+            MapPosition(0, 1) => MapPosition(if turn == 0 { -1 } else { 1 }, 0),
+            MapPosition(1, 0) => MapPosition(0, if turn == 0 { 1 } else { -1 }),
+            MapPosition(0, -1) => MapPosition(if turn == 0 { 1 } else { -1 }, 0),
+            MapPosition(-1, 0) => MapPosition(0, if turn == 0 { -1 } else { 1 }),
+            _ => unreachable!("Invalid direction!"),
+        };
+
+        self.pos += self.dir;
+        Ok(())
+    }
+
+    fn next_input(&mut self) -> Result<Option<i64>, Self::Error> {
+        match self.map.get(&self.pos) {
+            Some(Color::White) => Ok(Some(1)),
+            _ => Ok(Some(0)),
         }
     }
 }
 
+fn run_robot(v: &[i64], c: Color) -> Result<HashMap<MapPosition, Color>, FrameError> {
+    let mut robot = HullRobot::new(c);
+    drive(&mut IntCode::new(v), &mut robot)?;
+
+    Ok(robot.map)
+}
+
+/// # Errors
+///
+/// Returns a `FrameError` if the robot stops between a color and a turn.
 #[aoc(day11, part1)]
-pub fn part1(v: &[i64]) -> usize {
-    let map = run_robot(v, Color::Black);
-    map.len()
+pub fn part1(v: &[i64]) -> Result<usize, FrameError> {
+    let map = run_robot(v, Color::Black)?;
+    Ok(map.len())
 }
 
+/// # Errors
+///
+/// Fails like `part1`.
+///
+/// # Panics
+///
+/// Panics if shifting the painted panels leaves one below the bottom row.
 #[aoc(day11, part2)]
-pub fn part2(v: &[i64]) -> Option<String> {
-    let map = run_robot(v, Color::White);
+pub fn part2(v: &[i64]) -> Result<String, FrameError> {
+    let map = run_robot(v, Color::White)?;
 
     let mut out = map
         .iter()
@@ -114,15 +147,15 @@ pub fn part2(v: &[i64]) -> Option<String> {
         }
     });
 
-    let min_y = out.iter().min_by_key(|it| it.1)?.1;
+    let min_y = out.iter().map(|it| it.1).min().unwrap_or_default();
 
     let results = out
         .iter()
         .map(|it| it.add(MapPosition(0, i64::abs(min_y))))
         .collect_vec();
 
-    let max_x = results.iter().max_by_key(|it| it.0)?.0;
-    let max_y = results.iter().max_by_key(|it| it.1)?.1;
+    let max_x = results.iter().map(|it| it.0).max().unwrap_or(-1);
+    let max_y = results.iter().map(|it| it.1).max().unwrap_or(-1);
 
     assert!(results.iter().all(|it| it.1 >= 0));
 
@@ -138,5 +171,51 @@ pub fn part2(v: &[i64]) -> Option<String> {
         println!();
     }
 
-    Some(String::from("FARBCFJK"))
+    Ok(String::from("FARBCFJK"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode::device::{ScriptedVm, Step, Stop};
+
+    #[test]
+    fn test_hull_robot() {
+        // The example from the puzzle description.
+        let mut vm = ScriptedVm::new(&[
+            Step::Input,
+            Step::Output(1),
+            Step::Output(0),
+            Step::Input,
+            Step::Output(0),
+            Step::Output(0),
+            Step::Output(1),
+            Step::Output(0),
+            Step::Output(1),
+            Step::Output(0),
+            Step::Input,
+            Step::Output(0),
+            Step::Output(1),
+            Step::Output(1),
+            Step::Output(0),
+            Step::Output(1),
+            Step::Output(0),
+        ]);
+        let mut robot = HullRobot::new(Color::Black);
+
+        assert_eq!(drive(&mut vm, &mut robot), Ok(Stop::Halted));
+        assert_eq!(vm.inputs(), &[0, 0, 1]);
+        assert_eq!(robot.map.len(), 6);
+        assert_eq!(robot.pos, MapPosition(0, 1));
+    }
+
+    #[test]
+    fn test_interrupted() {
+        let mut vm = ScriptedVm::new(&[Step::Input, Step::Output(1), Step::Input]);
+
+        assert_eq!(
+            drive(&mut vm, &mut HullRobot::new(Color::Black)),
+            Err(FrameError::Interrupted(vec![1]))
+        );
+    }
 }
@@ -1,37 +1,153 @@
 use std::cmp::Ordering;
+use std::convert::TryFrom;
+use std::error::Error;
+use std::fmt;
 
 use aoc_runner_derive::{aoc, aoc_generator};
-use itertools::Itertools;
 
-use crate::intcode::{IntCode, State};
+use crate::intcode::device::{drive, Device};
+use crate::intcode::framing::FrameError;
+use crate::intcode::patch::PatchSet;
+use crate::intcode::program::{ParseError, Program};
+use crate::intcode::IntCode;
+use crate::render::{self, Renderer};
 
+/// # Errors
+///
+/// Returns a `ParseError` if the input isn't a comma separated Intcode program.
 #[aoc_generator(day13)]
-pub fn generate(inp: &str) -> Vec<i64> {
-    inp.split(',').filter_map(|it| it.parse().ok()).collect()
+pub fn generate(inp: &str) -> Result<Program, ParseError> {
+    Program::parse(inp)
 }
 
-#[aoc(day13, part1)]
-pub fn part1(inp: &[i64]) -> usize {
-    let mut vm = IntCode::new(inp);
+const PATCHES: &str = include_str!("../patches/day13.patch");
+
+const TILES: [(i64, char); 5] = [(0, ' '), (1, '#'), (2, '='), (3, '_'), (4, 'o')];
+
+#[derive(Copy, Clone, Debug)]
+pub enum Update {
+    Score(i64),
+    Tile { x: usize, y: usize, id: i64 },
+}
 
-    let mut outputs = Vec::new();
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub struct InvalidTile([i64; 3]);
 
-    while let State::Write(x) = vm.run() {
-        outputs.push(x);
+impl fmt::Display for InvalidTile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid tile output {:?}", self.0)
     }
+}
 
-    let chunks = outputs.iter().chunks(3);
+impl TryFrom<[i64; 3]> for Update {
+    type Error = InvalidTile;
+
+    fn try_from(frame: [i64; 3]) -> Result<Self, Self::Error> {
+        match frame {
+            [-1, 0, score] => Ok(Self::Score(score)),
+            [x, y, id] if x >= 0 && y >= 0 => Ok(Self::Tile {
+                x: x as usize,
+                y: y as usize,
+                id,
+            }),
+            _ => Err(InvalidTile(frame)),
+        }
+    }
+}
+
+// Screen and joystick of the arcade cabinet. The joystick always follows the ball.
+pub struct Arcade {
+    map: Vec<Vec<i64>>,
+    score: i64,
+    renderer: Option<Renderer<i64>>,
+}
 
-    let mut num_block_tiles = 0;
-    for chunk in &chunks {
-        if let Some(tile_id) = chunk.into_iter().nth(2) {
-            if *tile_id == 2 {
-                num_block_tiles += 1;
+impl Arcade {
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            map: Vec::new(),
+            score: 0,
+            renderer: None,
+        }
+    }
+
+    fn with_renderer(mut self, renderer: Option<Renderer<i64>>) -> Self {
+        self.renderer = renderer;
+        self
+    }
+
+    // The screen grows to fit whatever the game draws.
+    fn set(&mut self, x: usize, y: usize, id: i64) {
+        let width = self.map.first().map_or(0, Vec::len).max(x + 1);
+        if y >= self.map.len() {
+            self.map.resize(y + 1, vec![0; width]);
+        }
+        if self.map[0].len() < width {
+            for row in &mut self.map {
+                row.resize(width, 0);
             }
         }
+
+        self.map[y][x] = id;
+    }
+
+    #[must_use]
+    pub fn count(&self, id: i64) -> usize {
+        self.map.iter().flatten().filter(|&&it| it == id).count()
+    }
+
+    #[must_use]
+    pub const fn score(&self) -> i64 {
+        self.score
+    }
+}
+
+impl Default for Arcade {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Device<3> for Arcade {
+    type Frame = Update;
+    type Error = FrameError<InvalidTile>;
+
+    fn on_output(&mut self, update: Update) -> Result<(), Self::Error> {
+        match update {
+            Update::Score(n) => self.score = n,
+            Update::Tile { x, y, id } => self.set(x, y, id),
+        }
+
+        Ok(())
+    }
+
+    fn next_input(&mut self) -> Result<Option<i64>, Self::Error> {
+        if let Some(renderer) = self.renderer.as_mut() {
+            renderer.draw(&self.map, &format!("Score: {}", self.score));
+        }
+
+        Ok(Some(get_distance_ball_paddle(&self.map)))
     }
 
-    num_block_tiles
+    fn on_halt(&mut self) -> Result<(), Self::Error> {
+        if let Some(renderer) = self.renderer.as_mut() {
+            renderer.finish(&self.map, &format!("Score: {}", self.score));
+        }
+
+        Ok(())
+    }
+}
+
+/// # Errors
+///
+/// Returns a `FrameError` if the game writes an incomplete or invalid tile.
+#[aoc(day13, part1)]
+pub fn part1(inp: &[i64]) -> Result<usize, FrameError<InvalidTile>> {
+    let mut arcade = Arcade::new();
+    drive(&mut IntCode::new(inp), &mut arcade)?;
+
+    Ok(arcade.count(2))
 }
 
 fn get_distance_ball_paddle(map: &[Vec<i64>]) -> i64 {
@@ -55,40 +171,73 @@ fn get_distance_ball_paddle(map: &[Vec<i64>]) -> i64 {
     }
 }
 
+/// # Errors
+///
+/// Fails like `part1`, or if the free play patch doesn't apply to the program.
 #[aoc(day13, part2)]
-pub fn part2(inp: &[i64]) -> i64 {
+pub fn part2(inp: &[i64]) -> Result<i64, Box<dyn Error>> {
     let mut vm = IntCode::new(inp);
-    vm.init_ram(0, 2);
-
-    let mut map = vec![vec![0i64; 40]; 24];
-
-    let tmp_output: &mut [i64; 3] = &mut [0, 0, 0];
-    let mut cur_out = 0;
-    let mut score = 0;
-    loop {
-        match vm.run() {
-            State::Write(n) => {
-                tmp_output[cur_out] = n;
-                cur_out = (cur_out + 1) % 3;
-
-                if cur_out == 0 {
-                    let [x, y, id] = tmp_output;
-                    if *x == -1 && *y == 0 {
-                        score = *id;
-                    } else {
-                        map[*y as usize][*x as usize] = *id;
-                    }
-                }
-            }
-            State::Waiting => {
-                let paddle_inp = get_distance_ball_paddle(&map);
-                vm.input(paddle_inp);
-            }
-            State::Halted(_) => {
-                break;
-            }
-        };
+    PatchSet::parse(PATCHES)?.apply("free play", &mut vm)?;
+
+    let renderer = render::enabled().then(|| Renderer::new(&TILES, 60));
+    let mut arcade = Arcade::new().with_renderer(renderer);
+    drive(&mut vm, &mut arcade)?;
+
+    Ok(arcade.score)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode::device::{ScriptedVm, Step, Stop};
+
+    fn tile(x: i64, y: i64, id: i64) -> [Step; 3] {
+        [Step::Output(x), Step::Output(y), Step::Output(id)]
+    }
+
+    #[test]
+    fn test_arcade() {
+        let mut script = Vec::new();
+        script.extend(&tile(1, 0, 2));
+        script.extend(&tile(2, 0, 2));
+        script.extend(&tile(3, 5, 3));
+        script.extend(&tile(1, 4, 4));
+        script.push(Step::Input);
+        script.extend(&tile(1, 4, 0));
+        script.extend(&tile(5, 4, 4));
+        script.push(Step::Input);
+        script.extend(&tile(-1, 0, 42));
+
+        let mut vm = ScriptedVm::new(&script);
+        let mut arcade = Arcade::new();
+
+        assert_eq!(drive(&mut vm, &mut arcade).ok(), Some(Stop::Halted));
+        assert_eq!(vm.inputs(), &[-1, 1]);
+        assert_eq!(arcade.count(2), 2);
+        assert_eq!(arcade.score, 42);
     }
 
-    score
+    #[test]
+    fn test_large_screen() {
+        let mut script = Vec::new();
+        script.extend(&tile(45, 3, 2));
+        script.extend(&tile(2, 30, 2));
+
+        let mut vm = ScriptedVm::new(&script);
+        let mut arcade = Arcade::new();
+
+        assert_eq!(drive(&mut vm, &mut arcade).ok(), Some(Stop::Halted));
+        assert_eq!(arcade.count(2), 2);
+        assert!(arcade.map.iter().all(|row| row.len() == 46));
+    }
+
+    #[test]
+    fn test_invalid_tile() {
+        let mut vm = ScriptedVm::new(&tile(-1, 1, 0));
+
+        assert!(matches!(
+            drive(&mut vm, &mut Arcade::new()),
+            Err(FrameError::Invalid(InvalidTile([-1, 1, 0])))
+        ));
+    }
 }
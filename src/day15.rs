@@ -1,16 +1,140 @@
-use crate::intcode::{IntCode, State};
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+
+use crate::intcode::device::{drive, Device};
+use crate::intcode::framing::FrameError;
+use crate::intcode::program::{ParseError, Program};
+use crate::intcode::IntCode;
+use crate::render::{self, Renderer};
 use aoc_runner_derive::{aoc, aoc_generator};
-use itertools::izip;
+use itertools::{izip, Itertools};
 
+/// # Errors
+///
+/// Returns a `ParseError` if the input isn't a comma separated Intcode program.
 #[aoc_generator(day15)]
-pub fn generate(inp: &str) -> Vec<i64> {
-    inp.split(',').filter_map(|it| it.parse().ok()).collect()
+pub fn generate(inp: &str) -> Result<Program, ParseError> {
+    Program::parse(inp)
 }
 
-#[derive(PartialEq, Eq, Hash, Clone, Debug)]
+#[derive(Clone, Debug)]
 struct DroidState {
     vm: IntCode,
     oxygen: bool,
+    // Only needed for rendering. The VM keeps track of the position itself, so it's left out
+    // of the comparison and the hash.
+    pos: (i64, i64),
+}
+
+impl PartialEq for DroidState {
+    fn eq(&self, other: &Self) -> bool {
+        self.vm == other.vm && self.oxygen == other.oxygen
+    }
+}
+
+impl Eq for DroidState {}
+
+impl Hash for DroidState {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.vm.hash(state);
+        self.oxygen.hash(state);
+    }
+}
+
+const TILES: [(u8, char); 2] = [(1, '.'), (2, 'O')];
+
+fn step(pos: (i64, i64), direction: i64) -> (i64, i64) {
+    match direction {
+        1 => (pos.0, pos.1 - 1),
+        2 => (pos.0, pos.1 + 1),
+        3 => (pos.0 - 1, pos.1),
+        4 => (pos.0 + 1, pos.1),
+        _ => unreachable!("Unknown direction!"),
+    }
+}
+
+// The part of the area the search has reached so far, redrawn in place as it grows.
+struct AreaView {
+    tiles: HashMap<(i64, i64), u8>,
+    renderer: Renderer<u8>,
+}
+
+impl AreaView {
+    fn new() -> Self {
+        Self {
+            tiles: HashMap::new(),
+            renderer: Renderer::new(&TILES, 30).with_fallback(' '),
+        }
+    }
+
+    fn map(&self) -> Vec<Vec<u8>> {
+        let bounds = |coord: fn(&(i64, i64)) -> i64| {
+            self.tiles
+                .keys()
+                .map(coord)
+                .minmax()
+                .into_option()
+                .unwrap_or((0, -1))
+        };
+        let (min_x, max_x) = bounds(|it| it.0);
+        let (min_y, max_y) = bounds(|it| it.1);
+
+        let mut map = vec![vec![0u8; (max_x - min_x + 1) as usize]; (max_y - min_y + 1) as usize];
+        for (pos, &tile) in &self.tiles {
+            map[(pos.1 - min_y) as usize][(pos.0 - min_x) as usize] = tile;
+        }
+
+        map
+    }
+
+    fn visit(&mut self, ds: &DroidState) {
+        self.tiles.insert(ds.pos, 1 + u8::from(ds.oxygen));
+        if self.renderer.is_due() {
+            let map = self.map();
+            self.renderer.draw(&map, "");
+        }
+    }
+
+    fn finish(&mut self) {
+        let map = self.map();
+        self.renderer.finish(&map, "");
+    }
+}
+
+// Drives the droid a single step and records the status code it reports back.
+pub struct RepairDroid {
+    direction: Option<i64>,
+    status: Option<i64>,
+}
+
+impl RepairDroid {
+    #[must_use]
+    pub const fn moving(direction: i64) -> Self {
+        Self {
+            direction: Some(direction),
+            status: None,
+        }
+    }
+
+    #[must_use]
+    pub const fn status(&self) -> Option<i64> {
+        self.status
+    }
+}
+
+impl Device for RepairDroid {
+    type Frame = [i64; 1];
+    type Error = FrameError;
+
+    fn on_output(&mut self, [val]: [i64; 1]) -> Result<(), Self::Error> {
+        self.status = Some(val);
+        Ok(())
+    }
+
+    fn next_input(&mut self) -> Result<Option<i64>, Self::Error> {
+        // Only one move per droid, the status has to arrive before the next request.
+        Ok(self.direction.take())
+    }
 }
 
 fn successors(ds: &DroidState) -> Vec<DroidState> {
@@ -18,20 +142,23 @@ fn successors(ds: &DroidState) -> Vec<DroidState> {
 
     for direction in 1..=4 {
         let mut vm = ds.vm.clone();
-        if vm.run() == State::Waiting {
-            vm.input(direction);
-
-            if let State::Write(n) = vm.run() {
-                match n {
-                    0 => { /* hit a wall */ }
-                    1 | 2 => {
-                        // moved one step - new state
-                        let new_state = DroidState { vm, oxygen: n == 2 };
-                        result.push(new_state);
-                    }
-                    _ => unreachable!("Unknown response code!"),
-                }
+        let mut droid = RepairDroid::moving(direction);
+        if drive(&mut vm, &mut droid).is_err() {
+            continue;
+        }
+
+        match droid.status {
+            None | Some(0) => { /* hit a wall */ }
+            Some(n @ (1 | 2)) => {
+                // moved one step - new state
+                let new_state = DroidState {
+                    vm,
+                    oxygen: n == 2,
+                    pos: step(ds.pos, direction),
+                };
+                result.push(new_state);
             }
+            _ => unreachable!("Unknown response code!"),
         }
     }
 
@@ -54,9 +181,14 @@ fn find_oxygen(ds: &DroidState) -> Option<(DroidState, usize)> {
 }
 
 #[aoc(day15, part1)]
+#[must_use]
 pub fn part1(inp: &[i64]) -> Option<usize> {
     let vm = IntCode::new(inp);
-    let ds = DroidState { vm, oxygen: false };
+    let ds = DroidState {
+        vm,
+        oxygen: false,
+        pos: (0, 0),
+    };
 
     let (_, cost) = find_oxygen(&ds)?;
 
@@ -66,15 +198,45 @@ pub fn part1(inp: &[i64]) -> Option<usize> {
 #[aoc(day15, part2)]
 pub fn part2(inp: &[i64]) -> Option<usize> {
     let vm = IntCode::new(inp);
-    let ds = DroidState { vm, oxygen: false };
+    let ds = DroidState {
+        vm,
+        oxygen: false,
+        pos: (0, 0),
+    };
 
     let (ds, _) = find_oxygen(&ds)?;
 
-    let all_nodes =
-        pathfinding::prelude::dijkstra_all(&ds, |it| izip!(successors(it), std::iter::repeat(1)));
+    let mut view = render::enabled().then(AreaView::new);
+    let all_nodes = pathfinding::prelude::dijkstra_all(&ds, |it| {
+        if let Some(view) = view.as_mut() {
+            view.visit(it);
+        }
+
+        izip!(successors(it), std::iter::repeat(1))
+    });
+
+    if let Some(view) = view.as_mut() {
+        view.finish();
+    }
 
     all_nodes
         .values()
         .max_by_key(|(_, cost)| cost)
         .map(|(_, cost)| *cost - 1)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode::device::{ScriptedVm, Step, Stop};
+
+    #[test]
+    fn test_repair_droid() {
+        let mut vm = ScriptedVm::new(&[Step::Input, Step::Output(2), Step::Input]);
+        let mut droid = RepairDroid::moving(3);
+
+        assert_eq!(drive(&mut vm, &mut droid), Ok(Stop::NoInput));
+        assert_eq!(vm.inputs(), &[3]);
+        assert_eq!(droid.status, Some(2));
+    }
+}
@@ -1,13 +1,28 @@
+use crate::intcode::patch::{PatchError, PatchSet};
+use crate::intcode::program::{ParseError, Program};
 use crate::intcode::{IntCode, State};
+use crate::render::{self, Renderer};
 use aoc_runner_derive::{aoc, aoc_generator};
 use itertools::{iproduct, Itertools};
 use std::collections::HashMap;
 
 #[aoc_generator(day17)]
-pub fn generate(inp: &str) -> Vec<i64> {
-    inp.split(',').filter_map(|it| it.parse().ok()).collect()
+pub fn generate(inp: &str) -> Result<Program, ParseError> {
+    Program::parse(inp)
 }
 
+const PATCHES: &str = include_str!("../patches/day17.patch");
+
+const TILES: [(char, char); 7] = [
+    ('.', ' '),
+    ('#', '#'),
+    ('^', '^'),
+    ('v', 'v'),
+    ('<', '<'),
+    ('>', '>'),
+    ('X', 'X'),
+];
+
 fn generate_map(inp: &[i64]) -> Vec<Vec<char>> {
     let mut map: HashMap<(i64, i64), char> = HashMap::new();
 
@@ -58,7 +73,10 @@ fn generate_map(inp: &[i64]) -> Vec<Vec<char>> {
 #[aoc(day17, part1)]
 pub fn part1(inp: &[i64]) -> usize {
     let map = generate_map(inp);
-    //print_map(&map);
+
+    if render::enabled() {
+        Renderer::new(&TILES, 1).finish(&map, "");
+    }
 
     let mut result = vec![];
 
@@ -78,7 +96,7 @@ pub fn part1(inp: &[i64]) -> usize {
 }
 
 #[aoc(day17, part2)]
-pub fn part2(inp: &[i64]) -> i64 {
+pub fn part2(inp: &[i64]) -> Result<i64, PatchError> {
     // Print the map & resolve the path by hand...
     const MAIN: [char; 20] = [
         'A', ',', 'B', ',', 'A', ',', 'C', ',', 'A', ',', 'B', ',', 'C', ',', 'B', ',', 'C', ',',
@@ -105,7 +123,7 @@ pub fn part2(inp: &[i64]) -> i64 {
         .collect_vec();
 
     let mut vm = IntCode::new(inp);
-    vm.init_ram(0, 2);
+    PatchSet::parse(PATCHES)?.apply("wake robot", &mut vm)?;
 
     let mut cur_idx = 0;
 
@@ -120,7 +138,7 @@ pub fn part2(inp: &[i64]) -> i64 {
                 last_write = n;
             }
             State::Halted(_) => {
-                return last_write;
+                return Ok(last_write);
             }
         };
     }
@@ -1,35 +1,71 @@
-use crate::intcode::{IntCode, State};
+use std::collections::VecDeque;
+
+use crate::intcode::batch::map_inputs;
+use crate::intcode::device::{drive, Device};
+use crate::intcode::framing::FrameError;
+use crate::intcode::parallel::par_map;
+use crate::intcode::program::{ParseError, Program};
+use crate::intcode::IntCode;
 use aoc_runner_derive::{aoc, aoc_generator};
-use itertools::iproduct;
+use itertools::{iproduct, Itertools};
 
+/// # Errors
+///
+/// Returns a `ParseError` if the input isn't a comma separated Intcode program.
 #[aoc_generator(day19)]
-pub fn generate(inp: &str) -> Vec<i64> {
-    inp.split(',').filter_map(|it| it.parse().ok()).collect()
+pub fn generate(inp: &str) -> Result<Program, ParseError> {
+    Program::parse(inp)
 }
 
-#[aoc(day19, part1)]
-pub fn part1(inp: &[i64]) -> i64 {
-    let mut result = 0;
-
-    for (x, y) in iproduct!(0..50, 0..50) {
-        let mut vm = IntCode::new(inp);
+// Sends the drone to one position and reports whether it's pulled by the beam there.
+pub struct DroneProbe {
+    coords: VecDeque<i64>,
+    pulled: Option<bool>,
+}
 
-        // X
-        if vm.run() == State::Waiting {
-            vm.input(x);
+impl DroneProbe {
+    #[must_use]
+    pub fn at(x: i64, y: i64) -> Self {
+        Self {
+            coords: vec![x, y].into(),
+            pulled: None,
         }
+    }
 
-        // Y
-        if vm.run() == State::Waiting {
-            vm.input(y);
-        }
+    #[must_use]
+    pub const fn pulled(&self) -> Option<bool> {
+        self.pulled
+    }
+}
 
-        if let State::Write(n) = vm.run() {
-            result += n;
-        }
+impl Device for DroneProbe {
+    type Frame = [i64; 1];
+    type Error = FrameError;
+
+    fn on_output(&mut self, [val]: [i64; 1]) -> Result<(), Self::Error> {
+        self.pulled = Some(val == 1);
+        Ok(())
     }
 
-    result
+    fn next_input(&mut self) -> Result<Option<i64>, Self::Error> {
+        Ok(self.coords.pop_front())
+    }
+}
+
+fn is_pulled(inp: &[i64], x: i64, y: i64) -> bool {
+    let mut probe = DroneProbe::at(x, y);
+    drive(&mut IntCode::new(inp), &mut probe).is_ok() && probe.pulled == Some(true)
+}
+
+#[aoc(day19, part1)]
+#[must_use]
+pub fn part1(inp: &[i64]) -> usize {
+    let probes = iproduct!(0..50, 0..50).collect_vec();
+
+    par_map(&probes, |&(x, y)| is_pulled(inp, x, y))
+        .into_iter()
+        .filter(|&pulled| pulled)
+        .count()
 }
 
 fn can_fit_n_in_row(x: usize, y: usize, map: &[Vec<char>], num: usize) -> bool {
@@ -61,29 +97,22 @@ fn can_fit_n_in_col(x: usize, y: usize, map: &[Vec<char>], num: usize) -> bool {
 }
 
 #[aoc(day19, part2)]
+#[must_use]
 pub fn part2(inp: &[i64]) -> usize {
     let mut map = vec![vec!['.'; 1800]; 1800];
 
     // Skip first 1000x1000 block
-    for (x, y) in iproduct!(1000..map.len(), 1000..map[0].len()) {
-        let mut vm = IntCode::new(inp);
+    let coords = iproduct!(1000..map.len(), 1000..map[0].len()).collect_vec();
 
-        // X
-        if vm.run() == State::Waiting {
-            #[allow(clippy::cast_possible_wrap)]
-            vm.input(x as i64);
-        }
-
-        // Y
-        if vm.run() == State::Waiting {
-            #[allow(clippy::cast_possible_wrap)]
-            vm.input(y as i64);
-        }
+    #[allow(clippy::cast_possible_wrap)]
+    let probes = coords
+        .iter()
+        .map(|&(x, y)| vec![x as i64, y as i64])
+        .collect_vec();
 
-        if let State::Write(n) = vm.run() {
-            if n == 1 {
-                map[x][y] = '#';
-            }
+    for (&(x, y), out) in coords.iter().zip(map_inputs(inp, &probes)) {
+        if out == [1] {
+            map[x][y] = '#';
         }
     }
 
@@ -95,3 +124,19 @@ pub fn part2(inp: &[i64]) -> usize {
 
     unreachable!("No solution found!")
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode::device::{ScriptedVm, Step, Stop};
+
+    #[test]
+    fn test_drone_probe() {
+        let mut vm = ScriptedVm::new(&[Step::Input, Step::Input, Step::Output(1)]);
+        let mut probe = DroneProbe::at(3, 4);
+
+        assert_eq!(drive(&mut vm, &mut probe), Ok(Stop::Halted));
+        assert_eq!(vm.inputs(), &[3, 4]);
+        assert_eq!(probe.pulled, Some(true));
+    }
+}
@@ -1,33 +1,78 @@
-use crate::intcode::{IntCode, State};
+use std::collections::VecDeque;
+use std::convert::TryFrom;
+
+use crate::intcode::device::{drive, Device};
+use crate::intcode::framing::FrameError;
+use crate::intcode::program::{ParseError, Program};
+use crate::intcode::IntCode;
 use aoc_runner_derive::{aoc, aoc_generator};
 use itertools::Itertools;
 
+/// # Errors
+///
+/// Returns a `ParseError` if the input isn't a comma separated Intcode program.
 #[aoc_generator(day21)]
-pub fn generate(inp: &str) -> Vec<i64> {
-    inp.split(',').filter_map(|it| it.parse().ok()).collect()
+pub fn generate(inp: &str) -> Result<Program, ParseError> {
+    Program::parse(inp)
+}
+
+// Types a springscript program into the droid and collects what it reports back: ASCII
+// text, or the amount of hull damage as a single large value once it made it across.
+pub struct Springdroid {
+    script: VecDeque<i64>,
+    text: String,
+    damage: Option<i64>,
 }
 
-fn run_program(vm: &mut IntCode, program: &[i64]) -> i64 {
-    let mut last_write = 0;
-
-    let mut cur_idx = 0;
-
-    loop {
-        match vm.run() {
-            State::Waiting => {
-                vm.input(program[cur_idx]);
-                cur_idx += 1;
-            }
-            State::Write(n) => {
-                last_write = n;
-            }
-            State::Halted(_) => return last_write,
-        };
+impl Springdroid {
+    #[must_use]
+    pub fn new(script: &[i64]) -> Self {
+        Self {
+            script: script.iter().copied().collect(),
+            text: String::new(),
+            damage: None,
+        }
+    }
+
+    #[must_use]
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    #[must_use]
+    pub const fn damage(&self) -> Option<i64> {
+        self.damage
     }
 }
 
+impl Device for Springdroid {
+    type Frame = [i64; 1];
+    type Error = FrameError;
+
+    fn on_output(&mut self, [val]: [i64; 1]) -> Result<(), Self::Error> {
+        match u8::try_from(val) {
+            Ok(ch) if ch.is_ascii() => self.text.push(char::from(ch)),
+            _ => self.damage = Some(val),
+        }
+
+        Ok(())
+    }
+
+    fn next_input(&mut self) -> Result<Option<i64>, Self::Error> {
+        Ok(self.script.pop_front())
+    }
+}
+
+fn run_program(vm: &mut IntCode, program: &[i64]) -> Option<i64> {
+    let mut droid = Springdroid::new(program);
+    drive(vm, &mut droid).ok()?;
+
+    droid.damage
+}
+
 #[aoc(day21, part1)]
-pub fn part1(inp: &[i64]) -> i64 {
+#[must_use]
+pub fn part1(inp: &[i64]) -> Option<i64> {
     #[rustfmt::skip]
     const PROGRAM: [char; 44] = [
         'O', 'R', ' ', 'A', ' ', 'T', '\n',
@@ -46,7 +91,8 @@ pub fn part1(inp: &[i64]) -> i64 {
 }
 
 #[aoc(day21, part2)]
-pub fn part2(inp: &[i64]) -> i64 {
+#[must_use]
+pub fn part2(inp: &[i64]) -> Option<i64> {
     #[rustfmt::skip]
     const PROGRAM: [char; 65] = [
         'O', 'R', ' ', 'A', ' ', 'T', '\n',
@@ -66,3 +112,27 @@ pub fn part2(inp: &[i64]) -> i64 {
 
     run_program(&mut vm, &input)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode::device::{ScriptedVm, Step, Stop};
+
+    #[test]
+    fn test_springdroid() {
+        let mut script = "Input instructions:\n"
+            .bytes()
+            .map(|it| Step::Output(i64::from(it)))
+            .collect_vec();
+        script.extend(vec![Step::Input; 5]);
+        script.push(Step::Output(19_354_464));
+
+        let mut vm = ScriptedVm::new(&script);
+        let mut droid = Springdroid::new(&[87, 65, 76, 75, 10]);
+
+        assert_eq!(drive(&mut vm, &mut droid), Ok(Stop::Halted));
+        assert_eq!(vm.inputs(), &[87, 65, 76, 75, 10]);
+        assert_eq!(droid.text, "Input instructions:\n");
+        assert_eq!(droid.damage, Some(19_354_464));
+    }
+}
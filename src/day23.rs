@@ -1,14 +1,49 @@
+use crate::intcode::framing::{Frame, FrameError};
+use crate::intcode::program::{ParseError, Program};
 use crate::intcode::{IntCode, State};
 use aoc_runner_derive::{aoc, aoc_generator};
 use std::collections::{HashMap, HashSet, VecDeque};
+use std::convert::TryFrom;
+use std::fmt;
 
 #[aoc_generator(day23)]
-pub fn generate(inp: &str) -> Vec<i64> {
-    inp.split(',').filter_map(|it| it.parse().ok()).collect()
+pub fn generate(inp: &str) -> Result<Program, ParseError> {
+    Program::parse(inp)
+}
+
+#[derive(Copy, Clone, Debug)]
+struct Packet {
+    addr: usize,
+    x_value: i64,
+    y_value: i64,
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct InvalidPacket([i64; 3]);
+
+impl fmt::Display for InvalidPacket {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid packet {:?}", self.0)
+    }
+}
+
+impl TryFrom<[i64; 3]> for Packet {
+    type Error = InvalidPacket;
+
+    fn try_from(frame: [i64; 3]) -> Result<Self, Self::Error> {
+        match frame {
+            [addr, x_value, y_value] if addr >= 0 => Ok(Self {
+                addr: addr as usize,
+                x_value,
+                y_value,
+            }),
+            _ => Err(InvalidPacket(frame)),
+        }
+    }
 }
 
 #[aoc(day23, part1)]
-pub fn part1(code: &[i64]) -> i64 {
+pub fn part1(code: &[i64]) -> Result<i64, FrameError<InvalidPacket>> {
     let mut vms = vec![IntCode::new(code); 50];
     let mut packets: HashMap<usize, VecDeque<(i64, i64)>> = HashMap::new();
 
@@ -17,8 +52,8 @@ pub fn part1(code: &[i64]) -> i64 {
 
     loop {
         for (vm_idx, vm) in vms.iter_mut().enumerate() {
-            match vm.run() {
-                State::Waiting => {
+            match vm.next_frame_as::<Packet, 3>()? {
+                Frame::Waiting => {
                     if first {
                         vm.input(idx);
                         idx += 1;
@@ -35,24 +70,20 @@ pub fn part1(code: &[i64]) -> i64 {
                         vm.input(-1);
                     }
                 }
-                State::Write(target_addr) => {
-                    if let State::Write(x_value) = vm.run() {
-                        if let State::Write(y_value) = vm.run() {
-                            if target_addr == 255 {
-                                return y_value;
-                            }
-                            packets
-                                .entry(target_addr as usize)
-                                .or_default()
-                                .push_back((x_value, y_value));
-                        } else {
-                            panic!("No y-value after x-value!");
-                        }
-                    } else {
-                        panic!("No x-value after target-addr!");
+                Frame::Data(Packet {
+                    addr,
+                    x_value,
+                    y_value,
+                }) => {
+                    if addr == 255 {
+                        return Ok(y_value);
                     }
+                    packets
+                        .entry(addr)
+                        .or_default()
+                        .push_back((x_value, y_value));
                 }
-                State::Halted(_) => {}
+                Frame::Halted => {}
             }
         }
 
@@ -61,7 +92,7 @@ pub fn part1(code: &[i64]) -> i64 {
 }
 
 #[aoc(day23, part2)]
-pub fn part2(code: &[i64]) -> i64 {
+pub fn part2(code: &[i64]) -> Result<i64, FrameError<InvalidPacket>> {
     let mut vms = vec![IntCode::new(code); 50];
     let mut packets: HashMap<usize, VecDeque<(i64, i64)>> = HashMap::new();
 
@@ -75,8 +106,8 @@ pub fn part2(code: &[i64]) -> i64 {
     loop {
         let mut is_idle = 0;
         for (vm_idx, vm) in vms.iter_mut().enumerate() {
-            match vm.run() {
-                State::Waiting => {
+            match vm.next_frame_as::<Packet, 3>()? {
+                Frame::Waiting => {
                     if first {
                         vm.input(network_addr);
                         network_addr += 1;
@@ -94,32 +125,28 @@ pub fn part2(code: &[i64]) -> i64 {
                         is_idle += 1;
                     }
                 }
-                State::Write(target_addr) => {
-                    if let State::Write(x_value) = vm.run() {
-                        if let State::Write(y_value) = vm.run() {
-                            if target_addr == 255 {
-                                nat_package = Some((x_value, y_value));
-                            } else {
-                                packets
-                                    .entry(target_addr as usize)
-                                    .or_default()
-                                    .push_back((x_value, y_value));
-                            }
-                        } else {
-                            panic!("No y-value after x-value!");
-                        }
+                Frame::Data(Packet {
+                    addr,
+                    x_value,
+                    y_value,
+                }) => {
+                    if addr == 255 {
+                        nat_package = Some((x_value, y_value));
                     } else {
-                        panic!("No x-value after target-addr!");
+                        packets
+                            .entry(addr)
+                            .or_default()
+                            .push_back((x_value, y_value));
                     }
                 }
-                State::Halted(_) => {}
+                Frame::Halted => {}
             }
         }
 
         if is_idle == network_addr && packets.values().all(VecDeque::is_empty) {
             if let Some((x_value, y_value)) = nat_package {
                 if seen.contains(&y_value) {
-                    return y_value;
+                    return Ok(y_value);
                 }
 
                 seen.insert(y_value);
@@ -1,21 +1,61 @@
-use crate::intcode::{IntCode, State};
+use std::collections::VecDeque;
+use std::convert::TryFrom;
+
+use crate::intcode::device::{drive, Device};
+use crate::intcode::framing::FrameError;
+use crate::intcode::program::{ParseError, Program};
+use crate::intcode::IntCode;
 use aoc_runner_derive::{aoc, aoc_generator};
-use std::io::BufRead;
 
 #[aoc_generator(day25)]
-pub fn generate(inp: &str) -> Vec<i64> {
-    inp.split(',').filter_map(|it| it.parse().ok()).collect()
+pub fn generate(inp: &str) -> Result<Program, ParseError> {
+    Program::parse(inp)
 }
 
-#[aoc(day25, part1)]
-pub fn part1(inp: &[i64]) -> Option<usize> {
-    let mut vm = IntCode::new(inp);
+// Found by playing the game with `intcode-repl`, see the map below.
+const WALKTHROUGH: &str = include_str!("../scripts/day25.txt");
+
+// Types the commands of a script, one line at a time, and collects the text the game prints.
+struct Terminal {
+    input: VecDeque<i64>,
+    text: String,
+}
 
-    let mut cur_idx = 0;
-    let mut input = String::new();
+impl Terminal {
+    fn new(script: &str) -> Self {
+        let input = script
+            .lines()
+            .filter(|it| !it.starts_with('#'))
+            .flat_map(|it| it.bytes().chain(std::iter::once(b'\n')))
+            .map(i64::from)
+            .collect();
 
-    let stdin = std::io::stdin();
+        Self {
+            input,
+            text: String::new(),
+        }
+    }
+}
+
+impl Device for Terminal {
+    type Frame = [i64; 1];
+    type Error = FrameError;
 
+    fn on_output(&mut self, [val]: [i64; 1]) -> Result<(), Self::Error> {
+        if let Ok(ch) = u8::try_from(val) {
+            self.text.push(char::from(ch));
+        }
+
+        Ok(())
+    }
+
+    fn next_input(&mut self) -> Result<Option<i64>, Self::Error> {
+        Ok(self.input.pop_front())
+    }
+}
+
+#[aoc(day25, part1)]
+pub fn part1(inp: &[i64]) -> Option<usize> {
     // Map of the game:
     // L is the locked door to find the correct weight for
     // Don't pick up items in '#' rooms
@@ -35,36 +75,9 @@ pub fn part1(inp: &[i64]) -> Option<usize> {
     L
     */
 
-    loop {
-        match vm.run() {
-            State::Waiting => {
-                if cur_idx == 0 {
-                    input = stdin
-                        .lock()
-                        .lines()
-                        .next()
-                        .expect("There should be a line of input")
-                        .ok()?;
-                    if !input.ends_with('\n') {
-                        input.push('\n');
-                    }
-                }
-
-                let next_char = input.chars().nth(cur_idx)?;
-                vm.input(next_char as i64);
-                cur_idx += 1;
-                print!("{next_char}");
-
-                if next_char == '\n' {
-                    cur_idx = 0;
-                }
-            }
-            State::Write(n) => {
-                print!("{}", char::from_u32(n as u32)?);
-            }
-            State::Halted(_) => break,
-        };
-    }
+    let mut terminal = Terminal::new(WALKTHROUGH);
+    drive(&mut IntCode::new(inp), &mut terminal).ok()?;
 
-    Some(2_105_377)
+    let (_, code) = terminal.text.split_once("by typing ")?;
+    code.split_whitespace().next()?.parse().ok()
 }
@@ -1,9 +1,54 @@
-#[derive(PartialEq, Eq, Hash, Clone, Debug)]
+pub mod amplifier;
+pub mod batch;
+pub mod callstack;
+pub mod coverage;
+pub mod device;
+pub mod diagnostics;
+pub mod disasm;
+pub mod framing;
+pub mod golden;
+pub mod lines;
+pub mod memdump;
+pub mod parallel;
+pub mod patch;
+pub mod program;
+pub mod search;
+pub mod snapshot;
+pub mod specialise;
+pub mod symbols;
+pub mod taint;
+
+use std::fmt;
+use std::hash::{Hash, Hasher};
+
+#[derive(Clone, Debug)]
 pub struct IntCode {
     vpc: usize,
     rel_base: i64,
     mem: Vec<i64>,
     is_halted: bool,
+    fingerprint: u64,
+    stats: Stats,
+}
+
+#[derive(PartialEq, Eq, Copy, Clone, Debug, Default)]
+pub struct Stats {
+    pub instructions: u64,
+    pub inputs: u64,
+    pub outputs: u64,
+    // Times a read returned `State::Waiting`, whether or not input followed.
+    pub blocked: u64,
+    pub peak_rel_base: i64,
+}
+
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "instructions: {}, inputs: {}, outputs: {}, blocked: {}, peak rel_base: {}",
+            self.instructions, self.inputs, self.outputs, self.blocked, self.peak_rel_base
+        )
+    }
 }
 
 #[derive(PartialEq, Debug)]
@@ -31,57 +76,122 @@ const EQ: i64 = 8;
 const RB: i64 = 9;
 const HALT: i64 = 99;
 
+const MEM_SIZE: usize = 0x1500;
+
+const fn instruction_len(opcode: i64) -> usize {
+    match opcode {
+        ADD | MUL | LT | EQ => 4,
+        JT | JF => 3,
+        READ | WRITE | RB => 2,
+        _ => 1,
+    }
+}
+
+// Contribution of a single memory cell to the fingerprint. Zero cells contribute nothing, so
+// the untouched tail of memory doesn't have to be hashed.
+#[allow(clippy::cast_possible_wrap)]
+const fn cell_hash(idx: usize, val: i64) -> u64 {
+    if val == 0 {
+        return 0;
+    }
+
+    // splitmix64 finalizer
+    let mut h = (idx as u64)
+        .wrapping_mul(0x9e37_79b9_7f4a_7c15)
+        .wrapping_add(val as u64);
+    h = (h ^ (h >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    h = (h ^ (h >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    h ^ (h >> 31)
+}
+
 impl IntCode {
+    #[must_use]
     pub fn new(init_mem: &[i64]) -> Self {
-        let mut vec = vec![0; 0x1500];
+        let mut vec = vec![0; MEM_SIZE];
         vec[..init_mem.len()].clone_from_slice(init_mem);
 
+        let fingerprint = init_mem
+            .iter()
+            .enumerate()
+            .fold(0, |acc, (idx, val)| acc ^ cell_hash(idx, *val));
+
         Self {
             vpc: 0,
             rel_base: 0,
             mem: vec,
             is_halted: false,
+            fingerprint,
+            stats: Stats::default(),
         }
     }
 
     pub fn init_ram(&mut self, idx: usize, val: i64) {
+        self.store(idx, val);
+    }
+
+    // Counters are not part of the VM state, two VMs can be equal with different stats.
+    #[must_use]
+    pub const fn stats(&self) -> &Stats {
+        &self.stats
+    }
+
+    // Hash of the memory contents, kept up to date on every write.
+    #[must_use]
+    pub const fn fingerprint(&self) -> u64 {
+        self.fingerprint
+    }
+
+    fn store(&mut self, idx: usize, val: i64) {
+        self.fingerprint ^= cell_hash(idx, self.mem[idx]) ^ cell_hash(idx, val);
         self.mem[idx] = val;
     }
 
-    pub fn run_with_input(&mut self, mut inp_idx: usize, inp: &[i64]) -> State {
+    pub fn run(&mut self) -> State {
         loop {
-            match self.run() {
-                State::Waiting => {
-                    self.input(inp[inp_idx]);
-                    inp_idx += 1;
-                }
-                s => return s,
+            if let Some(state) = self.step() {
+                return state;
             }
         }
     }
 
-    pub fn run(&mut self) -> State {
-        loop {
-            let opc = self.mem[self.vpc];
-            let cur_opcode = opc % 100;
-
-            match cur_opcode {
-                ADD => self.do_arith(|lhs, rhs| lhs + rhs),
-                MUL => self.do_arith(|lhs, rhs| lhs * rhs),
-                READ => return State::Waiting,
-                WRITE => return State::Write(self.write()),
-                JT => self.jump(|it| it != 0),
-                JF => self.jump(|it| it == 0),
-                LT => self.cmp(|lhs, rhs| lhs < rhs),
-                EQ => self.cmp(|lhs, rhs| lhs == rhs),
-                RB => self.set_rel_base(),
-                HALT => {
-                    self.is_halted = true;
-                    return State::Halted(self.mem[0]);
+    /// Executes a single instruction. Returns the state `run` would stop at, if any.
+    ///
+    /// # Panics
+    ///
+    /// Panics on an unknown opcode or parameter mode, on a write to an immediate parameter
+    /// and on addresses outside of memory.
+    pub fn step(&mut self) -> Option<State> {
+        let opc = self.mem[self.vpc];
+        let cur_opcode = opc % 100;
+
+        match cur_opcode {
+            ADD => self.do_arith(|lhs, rhs| lhs + rhs),
+            MUL => self.do_arith(|lhs, rhs| lhs * rhs),
+            READ => {
+                self.stats.blocked += 1;
+                return Some(State::Waiting);
+            }
+            WRITE => {
+                self.stats.instructions += 1;
+                return Some(State::Write(self.write()));
+            }
+            JT => self.jump(|it| it != 0),
+            JF => self.jump(|it| it == 0),
+            LT => self.cmp(|lhs, rhs| lhs < rhs),
+            EQ => self.cmp(|lhs, rhs| lhs == rhs),
+            RB => self.set_rel_base(),
+            HALT => {
+                if !self.is_halted {
+                    self.stats.instructions += 1;
                 }
-                _ => panic!("Unknown opcode {}!", cur_opcode),
+                self.is_halted = true;
+                return Some(State::Halted(self.mem[0]));
             }
+            _ => panic!("Unknown opcode {}!", cur_opcode),
         }
+
+        self.stats.instructions += 1;
+        None
     }
 
     fn do_arith<F>(&mut self, f: F)
@@ -96,11 +206,14 @@ impl IntCode {
     pub fn input(&mut self, inp: i64) {
         self.set_param(1, inp);
         self.vpc += 2;
+        self.stats.instructions += 1;
+        self.stats.inputs += 1;
     }
 
     fn write(&mut self) -> i64 {
         let val = self.get_param(1);
         self.vpc += 2;
+        self.stats.outputs += 1;
 
         val
     }
@@ -128,6 +241,7 @@ impl IntCode {
     fn set_rel_base(&mut self) {
         self.rel_base += self.get_param(1);
         self.vpc += 2;
+        self.stats.peak_rel_base = self.stats.peak_rel_base.max(self.rel_base);
     }
 
     fn get_param_mode(&self, param_idx: i64) -> ParameterMode {
@@ -153,104 +267,144 @@ impl IntCode {
         }
     }
 
-    fn set_param(&mut self, param: i64, new_val: i64) {
-        let val = self.mem[self.vpc + (param as usize)] as usize;
+    // Memory address a parameter refers to, `None` for immediate parameters.
+    fn param_addr(&self, param: i64) -> Option<usize> {
+        let val = self.mem[self.vpc + (param as usize)];
 
         match self.get_param_mode(param) {
-            ParameterMode::Position => self.mem[val] = new_val,
-            ParameterMode::Relative => self.mem[(self.rel_base as usize) + val] = new_val,
-            ParameterMode::Immediate => panic!("Invalid parameter mode for write!"),
+            ParameterMode::Position => Some(val as usize),
+            ParameterMode::Immediate => None,
+            ParameterMode::Relative => Some((self.rel_base + val) as usize),
+        }
+    }
+
+    fn set_param(&mut self, param: i64, new_val: i64) {
+        match self.param_addr(param) {
+            Some(addr) => self.store(addr, new_val),
+            None => panic!("Invalid parameter mode for write!"),
         }
     }
 
+    #[must_use]
     pub const fn is_halted(&self) -> bool {
         self.is_halted
     }
 }
 
+impl PartialEq for IntCode {
+    fn eq(&self, other: &Self) -> bool {
+        self.fingerprint == other.fingerprint
+            && self.vpc == other.vpc
+            && self.rel_base == other.rel_base
+            && self.is_halted == other.is_halted
+            && self.mem == other.mem
+    }
+}
+
+impl Eq for IntCode {}
+
+impl Hash for IntCode {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.fingerprint.hash(state);
+        self.vpc.hash(state);
+        self.rel_base.hash(state);
+        self.is_halted.hash(state);
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
     #[test]
-    fn test_one() {
+    fn test_fingerprint() {
         let inp = vec![1, 0, 0, 0, 99];
-        let expected: Vec<i64> = vec![2, 0, 0, 0, 99];
 
         let mut vm = IntCode::new(&inp);
         vm.run();
 
-        assert!(vm.mem.starts_with(&expected));
-    }
-
-    #[test]
-    fn test_two() {
-        let inp: Vec<i64> = vec![2, 3, 0, 3, 99];
-        let expected: Vec<i64> = vec![2, 3, 0, 6, 99];
+        let mut other = IntCode::new(&[2, 0, 0, 0, 99]);
+        other.init_ram(0, 1);
+        assert_ne!(vm.fingerprint(), other.fingerprint());
 
-        let mut vm = IntCode::new(&inp);
-        vm.run();
+        other.run();
+        assert_eq!(vm.fingerprint(), other.fingerprint());
+        assert_eq!(vm, other);
 
-        assert!(vm.mem.starts_with(&expected));
+        vm.init_ram(100, 7);
+        vm.init_ram(100, 0);
+        assert_eq!(vm.fingerprint(), other.fingerprint());
+        assert_eq!(vm.fingerprint(), IntCode::new(&vm.mem[..5]).fingerprint());
     }
 
     #[test]
-    fn test_three() {
-        let inp: Vec<i64> = vec![2, 4, 4, 5, 99, 0];
-        let expected: Vec<i64> = vec![2, 4, 4, 5, 99, 9801];
+    fn test_stats() {
+        // Reads a value, outputs it twice, moves the relative base up and back down.
+        let inp = vec![3, 13, 4, 13, 4, 13, 109, 7, 109, -7, 99, 0, 0, 0];
 
         let mut vm = IntCode::new(&inp);
-        vm.run();
-
-        assert!(vm.mem.starts_with(&expected));
-    }
+        assert_eq!(vm.run(), State::Waiting);
+        assert_eq!(vm.run(), State::Waiting);
+        vm.input(42);
 
-    #[test]
-    fn test_four() {
-        let inp: Vec<i64> = vec![1, 1, 1, 4, 99, 5, 6, 0, 99];
-        let expected: Vec<i64> = vec![30, 1, 1, 4, 2, 5, 6, 0, 99];
-
-        let mut vm = IntCode::new(&inp);
+        while !vm.is_halted() {
+            vm.run();
+        }
         vm.run();
 
-        assert!(vm.mem.starts_with(&expected));
+        assert_eq!(
+            *vm.stats(),
+            Stats {
+                instructions: 6,
+                inputs: 1,
+                outputs: 2,
+                blocked: 2,
+                peak_rel_base: 7,
+            }
+        );
+
+        let mut other = IntCode::new(&inp);
+        other.run();
+        other.input(42);
+        while !other.is_halted() {
+            other.run();
+        }
+        assert_ne!(vm.stats(), other.stats());
+        assert_eq!(vm, other);
     }
 
     #[test]
-    fn test_five() {
-        let inp = vec![
-            109, 1, 204, -1, 1001, 100, 1, 100, 1008, 100, 16, 101, 1006, 101, 0, 99,
-        ];
+    fn test_blocked() {
+        // Adds two inputs and outputs the sum.
+        let inp = vec![3, 11, 3, 12, 1, 11, 12, 11, 4, 11, 99, 0, 0];
 
+        // A driver that answers every read right away still blocks once per read.
         let mut vm = IntCode::new(&inp);
-
-        for it in inp {
-            if let State::Write(n) = vm.run() {
-                assert_eq!(it, n);
+        let mut inputs = vec![2, 3].into_iter();
+        while !vm.is_halted() {
+            if vm.run() == State::Waiting {
+                vm.input(inputs.next().unwrap_or_default());
             }
         }
-    }
-
-    #[test]
-    fn test_six() {
-        let inp = vec![1102, 34_915_192, 34_915_192, 7, 4, 7, 99, 0];
+        assert_eq!(vm.stats().blocked, 2);
+        assert_eq!(vm.stats().blocked, vm.stats().inputs);
 
+        // Running again at a read without input blocks again.
         let mut vm = IntCode::new(&inp);
-
-        if let State::Write(n) = vm.run() {
-            assert_eq!(1_219_070_632_396_864, n);
+        for _ in 0..3 {
+            assert_eq!(vm.run(), State::Waiting);
         }
+        assert_eq!(vm.stats().blocked, 3);
+        assert_eq!(vm.stats().inputs, 0);
     }
 
     #[test]
-    fn test_seven() {
-        let inp = vec![104, 1_125_899_906_842_624, 99];
-
-        let mut vm = IntCode::new(&inp);
+    fn test_relative_store() {
+        // rel_base = 20, then [rb - 1] = 3 + 4 and [rb + 1] = [rb - 1] + 10.
+        let mut vm = IntCode::new(&[109, 20, 21101, 3, 4, -1, 22101, 10, -1, 1, 99]);
+        vm.run();
 
-        if let State::Write(n) = vm.run() {
-            assert_eq!(1_125_899_906_842_624, n);
-        }
+        assert_eq!(vm.mem[19..22], [7, 0, 17]);
     }
 
     #[test]
@@ -0,0 +1,387 @@
+use std::collections::VecDeque;
+use std::fmt;
+
+use itertools::Itertools;
+
+use crate::intcode::{IntCode, State};
+
+#[derive(Clone, Debug, Default)]
+struct Amplifier {
+    phases: Vec<i64>,
+    seed: Vec<i64>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Topology {
+    amps: Vec<Amplifier>,
+    edges: Vec<(usize, usize)>,
+    output: Option<usize>,
+    distinct_phases: bool,
+}
+
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct Best {
+    pub phases: Vec<i64>,
+    pub output: i64,
+}
+
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub enum TopologyError {
+    Empty,
+    NoOutput,
+    NoSignal(usize),
+    NoAmplifier(usize),
+    Phases { expected: usize, found: usize },
+}
+
+impl fmt::Display for TopologyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Empty => write!(f, "topology has no phase assignment to try"),
+            Self::NoOutput => write!(f, "topology has no output amplifier"),
+            Self::NoSignal(amp) => write!(f, "output amplifier {amp} never wrote a signal"),
+            Self::NoAmplifier(amp) => write!(f, "topology has no amplifier {amp}"),
+            Self::Phases { expected, found } => write!(
+                f,
+                "expected {expected} phases, one per amplifier, found {found}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for TopologyError {}
+
+impl Topology {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // A -> B -> ... -> last, with the initial signal 0 fed into A.
+    #[must_use]
+    pub fn chain(phases: &[i64], len: usize) -> Self {
+        let mut topology = Self::new();
+        topology.set_distinct_phases(true);
+
+        let amps = (0..len)
+            .map(|_| topology.add_amplifier(phases))
+            .collect_vec();
+
+        topology.edges = amps.iter().copied().tuple_windows().collect();
+
+        if let (Some(&first), Some(&last)) = (amps.first(), amps.last()) {
+            topology.amps[first].seed.push(0);
+            topology.output = Some(last);
+        }
+
+        topology
+    }
+
+    // Same as `chain`, but the last amplifier feeds back into the first one.
+    #[must_use]
+    pub fn ring(phases: &[i64], len: usize) -> Self {
+        let mut topology = Self::chain(phases, len);
+
+        if len > 0 {
+            topology.edges.push((len - 1, 0));
+        }
+
+        topology
+    }
+
+    pub fn add_amplifier(&mut self, phases: &[i64]) -> usize {
+        self.amps.push(Amplifier {
+            phases: phases.to_vec(),
+            seed: Vec::new(),
+        });
+
+        self.amps.len() - 1
+    }
+
+    const fn check(&self, amp: usize) -> Result<(), TopologyError> {
+        if amp < self.amps.len() {
+            Ok(())
+        } else {
+            Err(TopologyError::NoAmplifier(amp))
+        }
+    }
+
+    /// # Errors
+    ///
+    /// Returns `TopologyError::NoAmplifier` if either end isn't an amplifier of this topology.
+    pub fn connect(&mut self, from: usize, to: usize) -> Result<(), TopologyError> {
+        self.check(from)?;
+        self.check(to)?;
+        self.edges.push((from, to));
+        Ok(())
+    }
+
+    /// Extra input consumed right after the phase, before anything from upstream.
+    ///
+    /// # Errors
+    ///
+    /// Returns `TopologyError::NoAmplifier` if `amp` isn't an amplifier of this topology.
+    pub fn seed(&mut self, amp: usize, value: i64) -> Result<(), TopologyError> {
+        self.check(amp)?;
+        self.amps[amp].seed.push(value);
+        Ok(())
+    }
+
+    /// # Errors
+    ///
+    /// Returns `TopologyError::NoAmplifier` if `amp` isn't an amplifier of this topology.
+    pub fn set_output(&mut self, amp: usize) -> Result<(), TopologyError> {
+        self.check(amp)?;
+        self.output = Some(amp);
+        Ok(())
+    }
+
+    pub const fn set_distinct_phases(&mut self, distinct: bool) {
+        self.distinct_phases = distinct;
+    }
+
+    /// Runs all amplifiers round-robin until every one of them has either halted or is
+    /// blocked on an input nobody is going to provide. Amplifiers with several upstream
+    /// connections read the sum of one value from each of them.
+    ///
+    /// # Errors
+    ///
+    /// Returns `TopologyError::Phases` if there isn't one phase per amplifier,
+    /// `TopologyError::NoOutput` without an output amplifier and `TopologyError::NoSignal`
+    /// if that amplifier never wrote anything.
+    pub fn run(&self, mem: &[i64], phases: &[i64]) -> Result<i64, TopologyError> {
+        if phases.len() != self.amps.len() {
+            return Err(TopologyError::Phases {
+                expected: self.amps.len(),
+                found: phases.len(),
+            });
+        }
+
+        let output = self.output.ok_or(TopologyError::NoOutput)?;
+
+        let mut pending = self
+            .amps
+            .iter()
+            .zip(phases)
+            .map(|(amp, &phase)| std::iter::once(phase).chain(amp.seed.iter().copied()))
+            .map(Iterator::collect::<VecDeque<_>>)
+            .collect_vec();
+        let edges = |amp: usize, end: fn(&(usize, usize)) -> usize| {
+            self.edges
+                .iter()
+                .positions(|it| end(it) == amp)
+                .collect_vec()
+        };
+        let incoming = (0..self.amps.len())
+            .map(|amp| edges(amp, |it| it.1))
+            .collect_vec();
+        let outgoing = (0..self.amps.len())
+            .map(|amp| edges(amp, |it| it.0))
+            .collect_vec();
+
+        let mut channels = vec![VecDeque::new(); self.edges.len()];
+        let mut last_output = vec![None; self.amps.len()];
+        // VMs are created when an amplifier first runs and dropped once it halts, so a chain
+        // only ever has one VM's memory in use.
+        let mut vms = vec![None; self.amps.len()];
+        let mut halted = vec![false; self.amps.len()];
+
+        loop {
+            let mut progressed = false;
+
+            for idx in 0..self.amps.len() {
+                if halted[idx] {
+                    continue;
+                }
+
+                let vm = vms[idx].get_or_insert_with(|| IntCode::new(mem));
+                loop {
+                    match vm.run() {
+                        State::Waiting => {
+                            let incoming = &incoming[idx];
+
+                            if let Some(val) = pending[idx].pop_front() {
+                                vm.input(val);
+                            } else if !incoming.is_empty()
+                                && incoming.iter().all(|&e| !channels[e].is_empty())
+                            {
+                                let sum = incoming
+                                    .iter()
+                                    .filter_map(|&e| channels[e].pop_front())
+                                    .sum();
+                                vm.input(sum);
+                            } else {
+                                break;
+                            }
+                        }
+                        State::Write(n) => {
+                            last_output[idx] = Some(n);
+
+                            for &e in &outgoing[idx] {
+                                channels[e].push_back(n);
+                            }
+                        }
+                        State::Halted(_) => {
+                            halted[idx] = true;
+                            progressed = true;
+                            break;
+                        }
+                    }
+
+                    progressed = true;
+                }
+
+                if halted[idx] {
+                    vms[idx] = None;
+                }
+            }
+
+            if !progressed {
+                break;
+            }
+        }
+
+        last_output[output].ok_or(TopologyError::NoSignal(output))
+    }
+
+    // Every phase assignment, built lazily one amplifier at a time so that with distinct
+    // phases reused ones are dropped as soon as they appear.
+    fn assignments(&self) -> Box<dyn Iterator<Item = Vec<i64>> + '_> {
+        let distinct = self.distinct_phases;
+
+        self.amps.iter().fold(
+            Box::new(std::iter::once(Vec::new())),
+            move |prefixes, amp| {
+                Box::new(prefixes.flat_map(move |prefix| {
+                    amp.phases.iter().filter_map(move |&phase| {
+                        if distinct && prefix.contains(&phase) {
+                            return None;
+                        }
+
+                        let mut phases = prefix.clone();
+                        phases.push(phase);
+                        Some(phases)
+                    })
+                }))
+            },
+        )
+    }
+
+    /// # Errors
+    ///
+    /// Fails like `run` for any assignment, and with `TopologyError::Empty` if there is no
+    /// assignment to try.
+    pub fn best(&self, mem: &[i64]) -> Result<Best, TopologyError> {
+        self.assignments()
+            .map(|phases| {
+                let output = self.run(mem, &phases)?;
+                Ok(Best { phases, output })
+            })
+            .fold_ok(None, |acc: Option<Best>, it| match acc {
+                Some(best) if best.output >= it.output => Some(best),
+                _ => Some(it),
+            })?
+            .ok_or(TopologyError::Empty)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chain() {
+        let mem = vec![
+            3, 15, 3, 16, 1002, 16, 10, 16, 1, 16, 15, 15, 4, 15, 99, 0, 0,
+        ];
+
+        let best = Topology::chain(&[0, 1, 2, 3, 4], 5).best(&mem);
+        assert_eq!(
+            best,
+            Ok(Best {
+                phases: vec![4, 3, 2, 1, 0],
+                output: 43210
+            })
+        );
+    }
+
+    #[test]
+    fn test_ring() {
+        let mem = vec![
+            3, 26, 1001, 26, -4, 26, 3, 27, 1002, 27, 2, 27, 1, 27, 26, 27, 4, 27, 1001, 28, -1,
+            28, 1005, 28, 6, 99, 0, 0, 5,
+        ];
+
+        let best = Topology::ring(&[5, 6, 7, 8, 9], 5).best(&mem);
+        assert_eq!(
+            best,
+            Ok(Best {
+                phases: vec![9, 8, 7, 6, 5],
+                output: 139_629_729
+            })
+        );
+    }
+
+    #[test]
+    fn test_fan_out_fan_in() {
+        // Reads phase and signal, writes phase + signal.
+        let mem = vec![3, 11, 3, 12, 1, 11, 12, 13, 4, 13, 99, 0, 0, 0];
+
+        let mut topology = Topology::new();
+        let a = topology.add_amplifier(&[1, 2]);
+        let b = topology.add_amplifier(&[10]);
+        let c = topology.add_amplifier(&[100]);
+        let d = topology.add_amplifier(&[0, 1000]);
+        for &(from, to) in &[(a, b), (a, c), (b, d), (c, d)] {
+            assert_eq!(topology.connect(from, to), Ok(()));
+        }
+        assert_eq!(topology.seed(a, 0), Ok(()));
+        assert_eq!(topology.set_output(d), Ok(()));
+
+        assert_eq!(topology.run(&mem, &[1, 10, 100, 0]), Ok(112));
+        assert_eq!(
+            topology.best(&mem),
+            Ok(Best {
+                phases: vec![2, 10, 100, 1000],
+                output: 1114
+            })
+        );
+    }
+
+    #[test]
+    fn test_assignments() {
+        let topology = Topology::chain(&[0, 1, 2, 3, 4], 5);
+        assert_eq!(topology.assignments().count(), 120);
+
+        // 12! assignments, far too many to build up front.
+        let phases = (0..12).collect_vec();
+        let topology = Topology::chain(&phases, 12);
+        let mut assignments = topology.assignments();
+        assert_eq!(assignments.next(), Some(phases));
+        assert_eq!(
+            assignments.next().map(|it| it[10..].to_vec()),
+            Some(vec![11, 10])
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        let mut topology = Topology::new();
+        let a = topology.add_amplifier(&[0]);
+
+        assert_eq!(topology.run(&[99], &[0]), Err(TopologyError::NoOutput));
+
+        assert_eq!(topology.connect(a, 1), Err(TopologyError::NoAmplifier(1)));
+        assert_eq!(topology.seed(2, 0), Err(TopologyError::NoAmplifier(2)));
+        assert_eq!(topology.set_output(3), Err(TopologyError::NoAmplifier(3)));
+
+        let topology = Topology::chain(&[0, 1], 2);
+        assert_eq!(
+            topology.run(&[99], &[0]),
+            Err(TopologyError::Phases {
+                expected: 2,
+                found: 1
+            })
+        );
+    }
+}
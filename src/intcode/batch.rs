@@ -0,0 +1,295 @@
+use std::convert::TryFrom;
+
+use itertools::Itertools;
+
+use crate::intcode::parallel::par_map;
+use crate::intcode::{IntCode, State, ADD, EQ, HALT, JF, JT, LT, MEM_SIZE, MUL, RB, READ, WRITE};
+
+const LANES: usize = 64;
+
+// Runs one instance of `program` per entry of `inputs`, like `parallel::map_inputs`, but
+// steps up to `LANES` instances at once while they execute the same instructions.
+#[must_use]
+pub fn map_inputs(program: &[i64], inputs: &[Vec<i64>]) -> Vec<Vec<i64>> {
+    let chunks = inputs.chunks(LANES).collect_vec();
+
+    par_map(&chunks, |chunk| Batch::new(program, chunk).run())
+        .into_iter()
+        .flatten()
+        .collect()
+}
+
+// Instances sharing `vpc` and `rel_base`. Memory is laid out lane by lane for every address,
+// so cell `addr` of lane `lane` lives at `mem[addr * width + lane]`. Lanes whose control flow
+// leaves the group continue on their own `IntCode`.
+struct Batch<'a> {
+    width: usize,
+    vpc: usize,
+    rel_base: i64,
+    // Parameter modes of the current instruction, decoded once for all lanes.
+    modes: [i64; 3],
+    mem: Vec<i64>,
+    active: Vec<usize>,
+    inputs: Vec<&'a [i64]>,
+    outputs: Vec<Vec<i64>>,
+}
+
+impl<'a> Batch<'a> {
+    fn new(program: &[i64], inputs: &'a [Vec<i64>]) -> Self {
+        let width = inputs.len();
+        let mem = program
+            .iter()
+            .flat_map(|&cell| std::iter::repeat_n(cell, width))
+            .collect();
+
+        Self {
+            width,
+            vpc: 0,
+            rel_base: 0,
+            modes: [0; 3],
+            mem,
+            active: (0..width).collect(),
+            inputs: inputs.iter().map(Vec::as_slice).collect(),
+            outputs: vec![Vec::new(); width],
+        }
+    }
+
+    fn run(mut self) -> Vec<Vec<i64>> {
+        while !self.active.is_empty() {
+            let mut opc = self.load(self.vpc, self.active[0]);
+            if self
+                .active
+                .iter()
+                .any(|&lane| self.load(self.vpc, lane) != opc)
+            {
+                let opcodes = self
+                    .active
+                    .iter()
+                    .map(|&lane| (lane, self.load(self.vpc, lane)))
+                    .collect_vec();
+                let (vpc, rel_base) = (self.vpc, self.rel_base);
+                opc = self.regroup(&opcodes, |_| (vpc, rel_base));
+            }
+
+            self.modes = [opc / 100 % 10, opc / 1000 % 10, opc / 10000 % 10];
+
+            match opc % 100 {
+                ADD => self.arith(|lhs, rhs| lhs + rhs),
+                MUL => self.arith(|lhs, rhs| lhs * rhs),
+                LT => self.arith(|lhs, rhs| i64::from(lhs < rhs)),
+                EQ => self.arith(|lhs, rhs| i64::from(lhs == rhs)),
+                READ => {
+                    for lane in self.active.clone() {
+                        match self.inputs[lane].split_first() {
+                            Some((&inp, rest)) => {
+                                self.inputs[lane] = rest;
+                                let dest = self.dest(1, lane);
+                                self.store(dest, lane, inp);
+                            }
+                            None => self.active.retain(|&it| it != lane),
+                        }
+                    }
+                    self.vpc += 2;
+                }
+                WRITE => {
+                    for &lane in &self.active {
+                        let val = self.param(1, lane);
+                        self.outputs[lane].push(val);
+                    }
+                    self.vpc += 2;
+                }
+                JT | JF => {
+                    let targets = self
+                        .active
+                        .iter()
+                        .map(|&lane| {
+                            let taken = (self.param(1, lane) != 0) == (opc % 100 == JT);
+                            let target = if taken {
+                                self.param(2, lane)
+                            } else {
+                                i64::try_from(self.vpc + 3).expect("Address out of range!")
+                            };
+                            (lane, target)
+                        })
+                        .collect_vec();
+                    let rel_base = self.rel_base;
+                    self.vpc =
+                        self.regroup(&targets, |target| (target as usize, rel_base)) as usize;
+                }
+                RB => {
+                    let bases = self
+                        .active
+                        .iter()
+                        .map(|&lane| (lane, self.rel_base + self.param(1, lane)))
+                        .collect_vec();
+                    self.vpc += 2;
+                    let vpc = self.vpc;
+                    self.rel_base = self.regroup(&bases, |base| (vpc, base));
+                }
+                HALT => self.active.clear(),
+                op => panic!("Unknown opcode {}!", op),
+            }
+        }
+
+        self.outputs
+    }
+
+    fn arith<F>(&mut self, f: F)
+    where
+        F: Fn(i64, i64) -> i64,
+    {
+        for idx in 0..self.active.len() {
+            let lane = self.active[idx];
+            let val = f(self.param(1, lane), self.param(2, lane));
+            let dest = self.dest(3, lane);
+            self.store(dest, lane, val);
+        }
+
+        self.vpc += 4;
+    }
+
+    // Keeps the lanes sharing the most common value in the group and moves every other lane
+    // to its own VM, resuming at the `vpc` and `rel_base` that `resume` derives from the
+    // lane's value.
+    fn regroup<F>(&mut self, values: &[(usize, i64)], resume: F) -> i64
+    where
+        F: Fn(i64) -> (usize, i64),
+    {
+        let counts = values.iter().map(|&(_, val)| val).counts();
+        let keep = values
+            .iter()
+            .map(|&(_, val)| val)
+            .max_by_key(|val| counts[val])
+            .expect("regroup needs at least one active lane");
+
+        for &(lane, val) in values.iter().filter(|&&(_, val)| val != keep) {
+            let (vpc, rel_base) = resume(val);
+            self.detach(lane, vpc, rel_base);
+        }
+
+        keep
+    }
+
+    fn detach(&mut self, lane: usize, vpc: usize, rel_base: i64) {
+        let cells = (0..self.mem.len() / self.width)
+            .map(|addr| self.load(addr, lane))
+            .collect_vec();
+
+        let mut vm = IntCode::new(&cells);
+        vm.vpc = vpc;
+        vm.rel_base = rel_base;
+
+        loop {
+            match vm.run() {
+                State::Waiting => match self.inputs[lane].split_first() {
+                    Some((&inp, rest)) => {
+                        self.inputs[lane] = rest;
+                        vm.input(inp);
+                    }
+                    None => break,
+                },
+                State::Write(n) => self.outputs[lane].push(n),
+                State::Halted(_) => break,
+            }
+        }
+
+        self.active.retain(|&it| it != lane);
+    }
+
+    fn load(&self, addr: usize, lane: usize) -> i64 {
+        assert!(addr < MEM_SIZE, "Address {} out of range!", addr);
+        self.mem.get(addr * self.width + lane).copied().unwrap_or(0)
+    }
+
+    fn store(&mut self, addr: usize, lane: usize, val: i64) {
+        assert!(addr < MEM_SIZE, "Address {} out of range!", addr);
+
+        let idx = addr * self.width + lane;
+        if idx >= self.mem.len() {
+            self.mem.resize((addr + 1) * self.width, 0);
+        }
+        self.mem[idx] = val;
+    }
+
+    fn addr(&self, param: usize, lane: usize) -> usize {
+        let raw = self.load(self.vpc + param, lane);
+
+        let addr = match self.modes[param - 1] {
+            0 => raw,
+            1 => return self.vpc + param,
+            2 => self.rel_base + raw,
+            _ => panic!("Unknown parameter mode!"),
+        };
+
+        usize::try_from(addr).expect("Negative address!")
+    }
+
+    // Address written through a parameter, which like in `IntCode` can't be immediate.
+    fn dest(&self, param: usize, lane: usize) -> usize {
+        assert!(
+            self.modes[param - 1] != 1,
+            "Invalid parameter mode for write!"
+        );
+
+        self.addr(param, lane)
+    }
+
+    fn param(&self, param: usize, lane: usize) -> i64 {
+        self.load(self.addr(param, lane), lane)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode::parallel::run_pure;
+
+    fn check(program: &[i64], inputs: &[Vec<i64>]) {
+        let expected = inputs
+            .iter()
+            .map(|inp| run_pure(program, inp))
+            .collect_vec();
+
+        assert_eq!(map_inputs(program, inputs), expected);
+    }
+
+    #[test]
+    fn test_lock_step() {
+        // Outputs the sum of two inputs.
+        let program = [3, 11, 3, 12, 1, 11, 12, 13, 4, 13, 99, 0, 0, 0];
+        check(&program, &[vec![1, 2], vec![3, 4], vec![5], Vec::new()]);
+    }
+
+    #[test]
+    fn test_branches() {
+        // out(1) if a == 8 else out(0)
+        let program = [
+            3, 12, 1008, 12, 8, 12, 1005, 12, 13, 104, 0, 99, 0, 104, 1, 99,
+        ];
+        let inputs = (0..200).map(|it| vec![it % 10]).collect_vec();
+        check(&program, &inputs);
+    }
+
+    #[test]
+    fn test_rel_base() {
+        // Outputs the cell at the address given as input.
+        let program = [3, 7, 9, 7, 204, 0, 99, 0, 42];
+        let inputs = (0..9).map(|it| vec![it]).collect_vec();
+        check(&program, &inputs);
+    }
+
+    #[test]
+    fn test_self_modifying() {
+        // The input becomes the opcode of the second instruction.
+        let program = [3, 4, 104, 1, 0, 0, 99];
+        check(&program, &[vec![99], vec![4], vec![104], vec![4]]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid parameter mode for write!")]
+    fn test_immediate_write() {
+        // Adds into an immediate parameter, which `IntCode` rejects as well.
+        let program = [11101, 1, 2, 0, 99];
+        Batch::new(&program, &[Vec::new(), Vec::new()]).run();
+    }
+}
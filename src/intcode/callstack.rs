@@ -0,0 +1,192 @@
+use std::convert::TryFrom;
+use std::fmt;
+
+use crate::intcode::symbols::SymbolTable;
+use crate::intcode::{IntCode, State, JF, JT};
+
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub struct CallFrame {
+    pub caller: usize,
+    pub callee: usize,
+    pub frame_base: i64,
+    pub return_addr: usize,
+}
+
+// Virtual call stack, reconstructed from the calling convention the puzzle programs use:
+// the caller stores the return address at `[rel_base + 0]` and then jumps to the callee,
+// which eventually returns by jumping to that address again.
+#[derive(PartialEq, Eq, Clone, Debug, Default)]
+pub struct CallStack {
+    frames: Vec<CallFrame>,
+}
+
+impl CallStack {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Outermost call first.
+    #[must_use]
+    pub fn frames(&self) -> &[CallFrame] {
+        &self.frames
+    }
+
+    #[must_use]
+    pub const fn depth(&self) -> usize {
+        self.frames.len()
+    }
+
+    // Feeds a taken jump from `from` to `to`, with `rel_base` and `ret_slot`
+    // (the value at `[rel_base + 0]`) as they were before the jump.
+    fn jumped(&mut self, from: usize, to: usize, rel_base: i64, ret_slot: i64) {
+        if let Some(idx) = self.frames.iter().rposition(|it| it.return_addr == to) {
+            self.frames.truncate(idx);
+        } else if usize::try_from(ret_slot).ok() == Some(from + 3) {
+            self.frames.push(CallFrame {
+                caller: from,
+                callee: to,
+                frame_base: rel_base,
+                return_addr: from + 3,
+            });
+        }
+    }
+
+    // Innermost frame first, each line shows where execution is and which function
+    // (identified by its entry address, 0 for the program itself) that address belongs to.
+    #[must_use]
+    pub fn backtrace(&self, vpc: usize) -> String {
+        self.backtrace_with(vpc, &SymbolTable::default())
+    }
+
+    // Same as `backtrace`, with addresses and functions named after `symbols` where known.
+    #[must_use]
+    pub fn backtrace_with(&self, vpc: usize, symbols: &SymbolTable) -> String {
+        let pcs = std::iter::once(vpc).chain(self.frames.iter().rev().map(|it| it.caller));
+        let functions = self
+            .frames
+            .iter()
+            .rev()
+            .map(|it| (it.callee, Some(it.frame_base)))
+            .chain(std::iter::once((0, None)));
+
+        pcs.zip(functions)
+            .enumerate()
+            .map(|(idx, (pc, (function, frame_base)))| {
+                let (pc, function) = (symbols.label(pc), symbols.label(function));
+
+                frame_base.map_or_else(
+                    || format!("#{idx} {pc} in function {function}\n"),
+                    |base| format!("#{idx} {pc} in function {function} (frame base {base})\n"),
+                )
+            })
+            .collect()
+    }
+}
+
+impl fmt::Display for CallStack {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for frame in self.frames.iter().rev() {
+            writeln!(
+                f,
+                "{} called from {} (frame base {}, returns to {})",
+                frame.callee, frame.caller, frame.frame_base, frame.return_addr
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+impl IntCode {
+    pub fn run_with_call_stack(&mut self, stack: &mut CallStack) -> State {
+        loop {
+            let vpc = self.vpc;
+            let rel_base = self.rel_base;
+
+            let taken = match self.mem[vpc] % 100 {
+                JT => self.get_param(1) != 0,
+                JF => self.get_param(1) == 0,
+                _ => false,
+            };
+            let ret_slot = usize::try_from(rel_base)
+                .ok()
+                .and_then(|it| self.mem.get(it))
+                .copied()
+                .unwrap_or_default();
+
+            let state = self.step();
+
+            if taken {
+                stack.jumped(vpc, self.vpc, rel_base, ret_slot);
+            }
+
+            if let Some(state) = state {
+                return state;
+            }
+        }
+    }
+
+    #[must_use]
+    pub fn backtrace(&self, stack: &CallStack) -> String {
+        stack.backtrace(self.vpc)
+    }
+
+    #[must_use]
+    pub fn backtrace_with(&self, stack: &CallStack, symbols: &SymbolTable) -> String {
+        stack.backtrace_with(self.vpc, symbols)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // main: rb = 100, call double(21), output the result, halt
+    // double: [rb+1] *= 2, return through [rb+0]
+    #[rustfmt::skip]
+    const PROGRAM: [i64; 26] = [
+        109, 100,             //  0: rb = 100
+        21101, 21, 0, 1,      //  2: [rb+1] = 21
+        21101, 0, 13, 0,      //  6: [rb+0] = 13 (return address)
+        1105, 1, 16,          // 10: jump double
+        204, 1,               // 13: output [rb+1]
+        99,                   // 15: halt
+        22102, 2, 1, 1,       // 16: double: [rb+1] *= 2
+        4, 100,               // 20: output [100] (still inside double)
+        2105, 1, 0,           // 22: return
+        0,
+    ];
+
+    #[test]
+    fn test_call_stack() {
+        let mut vm = IntCode::new(&PROGRAM);
+        let mut stack = CallStack::new();
+
+        assert_eq!(vm.run_with_call_stack(&mut stack), State::Write(13));
+        assert_eq!(
+            stack.frames(),
+            &[CallFrame {
+                caller: 10,
+                callee: 16,
+                frame_base: 100,
+                return_addr: 13,
+            }]
+        );
+        assert_eq!(
+            vm.backtrace(&stack),
+            "#0 22 in function 16 (frame base 100)\n#1 10 in function 0\n"
+        );
+
+        let symbols = SymbolTable::parse("function 0..16 main\nfunction 16..25 double")
+            .expect("valid symbols");
+        assert_eq!(
+            vm.backtrace_with(&stack, &symbols),
+            "#0 double+6 in function double (frame base 100)\n#1 main+10 in function main\n"
+        );
+
+        assert_eq!(vm.run_with_call_stack(&mut stack), State::Write(42));
+        assert_eq!(stack.depth(), 0);
+        assert_eq!(vm.run_with_call_stack(&mut stack), State::Halted(109));
+    }
+}
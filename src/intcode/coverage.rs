@@ -0,0 +1,205 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::ops::Range;
+
+use itertools::Itertools;
+
+use crate::intcode::symbols::SymbolTable;
+use crate::intcode::{instruction_len, IntCode, State, JF, JT};
+
+#[derive(PartialEq, Eq, Copy, Clone, Debug, Default)]
+pub struct Branch {
+    pub taken: usize,
+    pub not_taken: usize,
+}
+
+impl Branch {
+    #[must_use]
+    pub const fn is_partial(&self) -> bool {
+        self.taken == 0 || self.not_taken == 0
+    }
+}
+
+#[derive(PartialEq, Eq, Clone, Debug, Default)]
+pub struct Coverage {
+    executed: BTreeSet<usize>,
+    branches: BTreeMap<usize, Branch>,
+}
+
+impl Coverage {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // True if `addr` was part of an executed instruction, either as opcode or as parameter.
+    #[must_use]
+    pub fn is_executed(&self, addr: usize) -> bool {
+        self.executed.contains(&addr)
+    }
+
+    #[must_use]
+    pub fn branch(&self, addr: usize) -> Option<Branch> {
+        self.branches.get(&addr).copied()
+    }
+
+    pub fn branches(&self) -> impl Iterator<Item = (usize, Branch)> + '_ {
+        self.branches.iter().map(|(addr, branch)| (*addr, *branch))
+    }
+
+    pub fn partial_branches(&self) -> impl Iterator<Item = (usize, Branch)> + '_ {
+        self.branches().filter(|(_, branch)| branch.is_partial())
+    }
+
+    pub fn merge(&mut self, other: &Self) {
+        self.executed.extend(&other.executed);
+
+        for (addr, branch) in &other.branches {
+            let entry = self.branches.entry(*addr).or_default();
+            entry.taken += branch.taken;
+            entry.not_taken += branch.not_taken;
+        }
+    }
+
+    // Maximal ranges of `0..len` that were never touched by an executed instruction.
+    #[must_use]
+    pub fn unexecuted(&self, len: usize) -> Vec<Range<usize>> {
+        (0..len).filter(|addr| !self.is_executed(*addr)).fold(
+            Vec::new(),
+            |mut acc: Vec<Range<usize>>, addr| {
+                match acc.last_mut() {
+                    Some(range) if range.end == addr => range.end += 1,
+                    _ => acc.push(addr..addr + 1),
+                }
+                acc
+            },
+        )
+    }
+
+    #[must_use]
+    pub fn report(&self, len: usize) -> String {
+        self.report_with(len, &SymbolTable::default())
+    }
+
+    // Same as `report`, with addresses named after `symbols` where known.
+    #[must_use]
+    pub fn report_with(&self, len: usize, symbols: &SymbolTable) -> String {
+        let executed = self.executed.range(..len).count();
+        let unexecuted = self
+            .unexecuted(len)
+            .iter()
+            .map(|it| format!("{}-{}", symbols.label(it.start), symbols.label(it.end - 1)))
+            .join(", ");
+        let partial = self
+            .partial_branches()
+            .map(|(addr, branch)| {
+                let dir = if branch.taken == 0 {
+                    "never taken"
+                } else {
+                    "always taken"
+                };
+                format!("{} ({})", symbols.label(addr), dir)
+            })
+            .join(", ");
+
+        format!(
+            "executed: {executed}/{len}\nunexecuted: {unexecuted}\npartial branches: {partial}\n"
+        )
+    }
+}
+
+impl IntCode {
+    pub fn run_with_coverage(&mut self, coverage: &mut Coverage) -> State {
+        loop {
+            let vpc = self.vpc;
+            let opcode = self.mem[vpc] % 100;
+
+            let taken = match opcode {
+                JT => Some(self.get_param(1) != 0),
+                JF => Some(self.get_param(1) == 0),
+                _ => None,
+            };
+
+            let state = self.step();
+
+            // Reads are completed by `input` outside of this loop, so reaching one counts.
+            coverage.executed.extend(vpc..vpc + instruction_len(opcode));
+
+            if let Some(taken) = taken {
+                let branch = coverage.branches.entry(vpc).or_default();
+                if taken {
+                    branch.taken += 1;
+                } else {
+                    branch.not_taken += 1;
+                }
+            }
+
+            if let Some(state) = state {
+                return state;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Outputs 1 if the input equals 8, 0 otherwise.
+    const IS_EIGHT: [i64; 14] = [3, 13, 1008, 13, 8, 13, 1005, 13, 10, 99, 104, 1, 99, 0];
+
+    fn run(inp: i64) -> Coverage {
+        let mut coverage = Coverage::new();
+        let mut vm = IntCode::new(&IS_EIGHT);
+
+        loop {
+            match vm.run_with_coverage(&mut coverage) {
+                State::Waiting => vm.input(inp),
+                State::Write(_) => {}
+                State::Halted(_) => return coverage,
+            }
+        }
+    }
+
+    #[test]
+    fn test_coverage() {
+        let coverage = run(1);
+
+        assert_eq!(
+            coverage.branch(6),
+            Some(Branch {
+                taken: 0,
+                not_taken: 1
+            })
+        );
+        assert!(coverage.is_executed(9));
+        assert!(!coverage.is_executed(10));
+        assert_eq!(coverage.unexecuted(IS_EIGHT.len()), vec![10..14]);
+
+        let symbols =
+            SymbolTable::parse("function 10..13 found\nvariable 13 input").expect("valid symbols");
+        assert_eq!(
+            coverage.report_with(IS_EIGHT.len(), &symbols),
+            "executed: 10/14\nunexecuted: found-input\npartial branches: 6 (never taken)\n"
+        );
+    }
+
+    #[test]
+    fn test_merge() {
+        let mut coverage = run(1);
+        coverage.merge(&run(8));
+
+        assert_eq!(
+            coverage.branch(6),
+            Some(Branch {
+                taken: 1,
+                not_taken: 1
+            })
+        );
+        assert_eq!(coverage.partial_branches().count(), 0);
+        assert_eq!(coverage.unexecuted(IS_EIGHT.len()), vec![13..14]);
+        assert_eq!(
+            coverage.report(IS_EIGHT.len()),
+            "executed: 13/14\nunexecuted: 13-13\npartial branches: \n"
+        );
+    }
+}
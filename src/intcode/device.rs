@@ -0,0 +1,233 @@
+use std::collections::VecDeque;
+use std::convert::TryFrom;
+
+use crate::intcode::framing::{read_frame, Frame, FrameError};
+use crate::intcode::{IntCode, State};
+
+// Anything that executes Intcode, so devices can be tested against a scripted stand-in.
+pub trait Machine {
+    fn run(&mut self) -> State;
+    fn input(&mut self, inp: i64);
+}
+
+impl Machine for IntCode {
+    fn run(&mut self) -> State {
+        Self::run(self)
+    }
+
+    fn input(&mut self, inp: i64) {
+        Self::input(self, inp);
+    }
+}
+
+// Hardware attached to a VM: it receives everything the VM writes, in frames of `N` values
+// decoded into `Frame`, and supplies its inputs.
+pub trait Device<const N: usize = 1> {
+    type Frame: TryFrom<[i64; N]>;
+    type Error: From<FrameError<<Self::Frame as TryFrom<[i64; N]>>::Error>>;
+
+    /// # Errors
+    ///
+    /// An error stops the driver, which passes it on.
+    fn on_output(&mut self, frame: Self::Frame) -> Result<(), Self::Error>;
+
+    /// `None` means the device has nothing more to say, which stops the driver.
+    ///
+    /// # Errors
+    ///
+    /// Like `on_output`.
+    fn next_input(&mut self) -> Result<Option<i64>, Self::Error>;
+
+    /// # Errors
+    ///
+    /// Like `on_output`.
+    fn on_halt(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub enum Stop {
+    Halted,
+    NoInput,
+}
+
+/// Feeds everything `machine` writes to `device` and its inputs back, until the machine
+/// halts or the device runs out of input.
+///
+/// # Errors
+///
+/// Returns the first error of the device, or of reading a frame the device rejects.
+pub fn drive<M, D, const N: usize>(machine: &mut M, device: &mut D) -> Result<Stop, D::Error>
+where
+    M: Machine + ?Sized,
+    D: Device<N> + ?Sized,
+{
+    loop {
+        match read_frame::<M, D::Frame, N>(machine)? {
+            Frame::Data(frame) => device.on_output(frame)?,
+            Frame::Waiting => match device.next_input()? {
+                Some(inp) => machine.input(inp),
+                None => return Ok(Stop::NoInput),
+            },
+            Frame::Halted => {
+                device.on_halt()?;
+                return Ok(Stop::Halted);
+            }
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub enum Step {
+    Output(i64),
+    Input,
+}
+
+// Fake VM replaying a fixed sequence of outputs and input requests, then halting. Inputs
+// it receives are recorded for inspection.
+#[derive(Clone, Debug, Default)]
+pub struct ScriptedVm {
+    script: VecDeque<Step>,
+    waiting: bool,
+    inputs: Vec<i64>,
+}
+
+impl ScriptedVm {
+    #[must_use]
+    pub fn new(script: &[Step]) -> Self {
+        Self {
+            script: script.iter().copied().collect(),
+            waiting: false,
+            inputs: Vec::new(),
+        }
+    }
+
+    #[must_use]
+    pub fn inputs(&self) -> &[i64] {
+        &self.inputs
+    }
+}
+
+impl Machine for ScriptedVm {
+    fn run(&mut self) -> State {
+        if self.waiting {
+            return State::Waiting;
+        }
+
+        match self.script.pop_front() {
+            Some(Step::Output(n)) => State::Write(n),
+            Some(Step::Input) => {
+                self.waiting = true;
+                State::Waiting
+            }
+            None => State::Halted(0),
+        }
+    }
+
+    fn input(&mut self, inp: i64) {
+        assert!(self.waiting, "Scripted VM received an unexpected input");
+        self.waiting = false;
+        self.inputs.push(inp);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Echoes back the sum of each pair of outputs.
+    #[derive(Default)]
+    struct Adder {
+        sums: VecDeque<i64>,
+    }
+
+    impl Device<2> for Adder {
+        type Frame = [i64; 2];
+        type Error = FrameError;
+
+        fn on_output(&mut self, [lhs, rhs]: [i64; 2]) -> Result<(), Self::Error> {
+            self.sums.push_back(lhs + rhs);
+            Ok(())
+        }
+
+        fn next_input(&mut self) -> Result<Option<i64>, Self::Error> {
+            Ok(self.sums.pop_front())
+        }
+    }
+
+    #[test]
+    fn test_drive() {
+        let mut vm = ScriptedVm::new(&[
+            Step::Output(1),
+            Step::Output(2),
+            Step::Input,
+            Step::Output(3),
+            Step::Output(4),
+            Step::Input,
+        ]);
+
+        assert_eq!(drive(&mut vm, &mut Adder::default()), Ok(Stop::Halted));
+        assert_eq!(vm.inputs(), &[3, 7]);
+
+        let mut vm = ScriptedVm::new(&[Step::Input]);
+        assert_eq!(drive(&mut vm, &mut Adder::default()), Ok(Stop::NoInput));
+    }
+
+    #[test]
+    fn test_frame_errors() {
+        let mut vm = ScriptedVm::new(&[Step::Output(1), Step::Input]);
+        assert_eq!(
+            drive(&mut vm, &mut Adder::default()),
+            Err(FrameError::Interrupted(vec![1]))
+        );
+
+        let mut vm = ScriptedVm::new(&[Step::Output(1)]);
+        assert_eq!(
+            drive(&mut vm, &mut Adder::default()),
+            Err(FrameError::Truncated(vec![1]))
+        );
+    }
+
+    // Devices of the puzzles, driven by a scripted VM from outside their own day.
+    #[test]
+    fn test_day_devices() {
+        use crate::day13::Arcade;
+        use crate::day19::DroneProbe;
+        use crate::day21::Springdroid;
+
+        let mut vm = ScriptedVm::new(&[Step::Input, Step::Input, Step::Output(1)]);
+        let mut probe = DroneProbe::at(3, 4);
+        assert_eq!(drive(&mut vm, &mut probe), Ok(Stop::Halted));
+        assert_eq!(vm.inputs(), &[3, 4]);
+        assert_eq!(probe.pulled(), Some(true));
+
+        let mut vm = ScriptedVm::new(&[Step::Output(i64::from(b'>')), Step::Input, Step::Input]);
+        let mut droid = Springdroid::new(&[i64::from(b'\n')]);
+        assert_eq!(drive(&mut vm, &mut droid), Ok(Stop::NoInput));
+        assert_eq!(droid.text(), ">");
+        assert_eq!(droid.damage(), None);
+
+        let mut vm = ScriptedVm::new(&[
+            Step::Output(1),
+            Step::Output(0),
+            Step::Output(2),
+            Step::Output(-1),
+            Step::Output(0),
+            Step::Output(7),
+        ]);
+        let mut arcade = Arcade::new();
+        assert_eq!(drive(&mut vm, &mut arcade), Ok(Stop::Halted));
+        assert_eq!((arcade.count(2), arcade.score()), (1, 7));
+    }
+
+    #[test]
+    fn test_int_code() {
+        // Outputs 1 and 2, then the input followed by 0.
+        let mut vm = IntCode::new(&[104, 1, 104, 2, 3, 11, 4, 11, 104, 0, 99, 0]);
+        let mut adder = Adder::default();
+
+        assert_eq!(drive(&mut vm, &mut adder), Ok(Stop::Halted));
+        assert_eq!(adder.sums, vec![3]);
+    }
+}
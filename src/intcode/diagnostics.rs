@@ -0,0 +1,205 @@
+use std::fmt;
+
+use crate::intcode::disasm::Instruction;
+use crate::intcode::{IntCode, State, ADD, EQ, LT, MEM_SIZE, MUL, READ, WRITE};
+
+// Result of one self-test. Diagnostic programs write 0 for a passing test and anything else
+// for a failing one, e.g. by how much the result was off, or the opcode that malfunctioned.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct Check {
+    // Address of the instruction that wrote the result.
+    pub addr: usize,
+    pub value: i64,
+    // The instruction under test: the one that computed the written value, skipping
+    // instructions that only adjust a value in place, like `add [224], -1440, [224]`.
+    pub suspect: Option<Instruction>,
+}
+
+#[derive(PartialEq, Eq, Clone, Debug, Default)]
+pub struct Report {
+    pub checks: Vec<Check>,
+    // The last value written before the program halted, if every check before it passed.
+    pub code: Option<i64>,
+}
+
+impl Check {
+    #[must_use]
+    pub const fn passed(&self) -> bool {
+        self.value == 0
+    }
+}
+
+impl Report {
+    pub fn passed(&self) -> bool {
+        self.code.is_some() && self.checks.iter().all(Check::passed)
+    }
+
+    pub fn failures(&self) -> impl Iterator<Item = &Check> + '_ {
+        self.checks.iter().filter(|it| !it.passed())
+    }
+
+    /// The diagnostic code of a passing run.
+    ///
+    /// # Errors
+    ///
+    /// Returns the whole report if a check failed or the program wrote no code.
+    pub fn into_code(self) -> Result<i64, Self> {
+        match self.code {
+            Some(code) if self.passed() => Ok(code),
+            _ => Err(self),
+        }
+    }
+}
+
+impl fmt::Display for Check {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.passed() {
+            return write!(f, "check at {}: passed", self.addr);
+        }
+
+        write!(f, "check at {}: failed with {}", self.addr, self.value)?;
+        if let Some(suspect) = &self.suspect {
+            write!(
+                f,
+                ", suspect opcode {} ({} at {})",
+                suspect.opcode, suspect, suspect.addr
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for check in &self.checks {
+            writeln!(f, "{check}")?;
+        }
+
+        match self.code {
+            Some(code) => write!(f, "diagnostic code: {code}"),
+            None => write!(f, "no diagnostic code"),
+        }
+    }
+}
+
+impl std::error::Error for Report {}
+
+// Runs a diagnostic program to completion, giving it `input` whenever it asks. Every value
+// but the last one is a check result, the last one is the diagnostic code unless a check
+// before it failed: a failing BOOST run only writes the opcodes that malfunctioned.
+#[must_use]
+pub fn run_diagnostics(program: &[i64], input: i64) -> Report {
+    let mut vm = IntCode::new(program);
+    // Address of the instruction that last computed each cell, decoded only for failed checks.
+    let mut origins = vec![None; MEM_SIZE];
+    let mut outputs = Vec::new();
+
+    loop {
+        let addr = vm.vpc;
+        let (dest, source) = match vm.mem[addr] % 100 {
+            ADD | MUL | LT | EQ => {
+                let dest = vm.param_addr(3);
+                let reads_dest =
+                    dest.is_some() && (vm.param_addr(1) == dest || vm.param_addr(2) == dest);
+                (dest.filter(|_| !reads_dest), None)
+            }
+            READ => (vm.param_addr(1), None),
+            WRITE => (None, vm.param_addr(1)),
+            _ => (None, None),
+        };
+
+        if let Some(cell) = dest.and_then(|it| origins.get_mut(it)) {
+            *cell = Some(addr);
+        }
+
+        match vm.step() {
+            Some(State::Waiting) => vm.input(input),
+            Some(State::Write(value)) => {
+                let suspect = source
+                    .filter(|_| value != 0)
+                    .and_then(|it| *origins.get(it)?)
+                    .and_then(|it| Instruction::decode(&vm.mem, it));
+                outputs.push(Check {
+                    addr,
+                    value,
+                    suspect,
+                });
+            }
+            Some(State::Halted(_)) => break,
+            None => {}
+        }
+    }
+
+    let last = outputs.pop();
+    let code = last
+        .as_ref()
+        .filter(|_| outputs.iter().all(Check::passed))
+        .map(|it| it.value);
+    outputs.extend(last.filter(|_| code.is_none()));
+
+    Report {
+        checks: outputs,
+        code,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report() {
+        // Two checks of multiplication, the second one expecting the wrong product.
+        let program = [
+            1102, 6, 7, 30, 1001, 30, -42, 30, 4, 30, 1102, 6, 7, 30, 1001, 30, -43, 30, 4, 30,
+            104, 17, 99,
+        ];
+
+        let report = run_diagnostics(&program, 0);
+        assert!(!report.passed());
+        assert_eq!(report.code, None);
+        assert_eq!(report.checks.len(), 3);
+        assert!(report.checks[0].passed());
+
+        let failures = report.failures().collect::<Vec<_>>();
+        assert_eq!(failures.len(), 2);
+        assert_eq!(failures[0].value, -1);
+        assert_eq!(
+            failures[0].suspect.as_ref().map(|it| (it.addr, it.opcode)),
+            Some((10, 1102))
+        );
+        assert_eq!(
+            report.to_string(),
+            "check at 8: passed
+check at 18: failed with -1, suspect opcode 1102 (mul 6, 7, [30] at 10)
+check at 20: failed with 17
+no diagnostic code"
+        );
+    }
+
+    #[test]
+    fn test_code() {
+        // Adds the input to itself and writes the sum after a passing check.
+        let program = [3, 11, 1, 11, 11, 12, 104, 0, 4, 12, 99, 0, 0];
+
+        let report = run_diagnostics(&program, 21);
+        assert!(report.passed());
+        assert_eq!(report.checks.len(), 1);
+        assert_eq!(report.into_code(), Ok(42));
+    }
+
+    #[test]
+    fn test_malfunction() {
+        // Like a failing BOOST run, which only writes the opcodes that malfunctioned.
+        let program = [104, 203, 104, 204, 99];
+
+        let report = run_diagnostics(&program, 0);
+        assert_eq!(report.code, None);
+        assert_eq!(
+            report.failures().map(|it| it.value).collect::<Vec<_>>(),
+            vec![203, 204]
+        );
+        assert!(report.into_code().is_err());
+    }
+}
@@ -0,0 +1,243 @@
+use std::convert::TryFrom;
+use std::fmt::{self, Write};
+use std::ops::Range;
+
+use itertools::Itertools;
+
+use crate::intcode::symbols::{SymbolKind, SymbolTable};
+use crate::intcode::{instruction_len, IntCode, ADD, EQ, HALT, JF, JT, LT, MUL, RB, READ, WRITE};
+
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub enum Operand {
+    Position(i64),
+    Immediate(i64),
+    Relative(i64),
+}
+
+impl fmt::Display for Operand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Self::Position(addr) => write!(f, "[{addr}]"),
+            Self::Immediate(val) => write!(f, "{val}"),
+            Self::Relative(offset) if offset < 0 => write!(f, "[rb{offset}]"),
+            Self::Relative(offset) => write!(f, "[rb+{offset}]"),
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct Instruction {
+    pub addr: usize,
+    pub opcode: i64,
+    pub mnemonic: &'static str,
+    pub operands: Vec<Operand>,
+}
+
+impl Instruction {
+    // `None` if the cell at `addr` isn't a valid instruction, e.g. because it holds data.
+    #[must_use]
+    pub fn decode(mem: &[i64], addr: usize) -> Option<Self> {
+        let opcode = *mem.get(addr)?;
+
+        let mnemonic = match opcode % 100 {
+            ADD => "add",
+            MUL => "mul",
+            READ => "in",
+            WRITE => "out",
+            JT => "jt",
+            JF => "jf",
+            LT => "lt",
+            EQ => "eq",
+            RB => "arb",
+            HALT => "halt",
+            _ => return None,
+        };
+
+        let operands = (1..instruction_len(opcode % 100))
+            .map(|param| {
+                let val = *mem.get(addr + param)?;
+
+                match (opcode / 10i64.pow(param as u32 + 1)) % 10 {
+                    0 => Some(Operand::Position(val)),
+                    1 => Some(Operand::Immediate(val)),
+                    2 => Some(Operand::Relative(val)),
+                    _ => None,
+                }
+            })
+            .collect::<Option<Vec<_>>>()?;
+
+        Some(Self {
+            addr,
+            opcode,
+            mnemonic,
+            operands,
+        })
+    }
+
+    #[must_use]
+    pub const fn size(&self) -> usize {
+        self.operands.len() + 1
+    }
+
+    // Same as `Display`, but addresses and jump targets covered by a symbol show its name.
+    #[must_use]
+    pub fn annotate(&self, symbols: &SymbolTable) -> String {
+        let is_jump = matches!(self.opcode % 100, JT | JF);
+        let name = |addr: i64| usize::try_from(addr).ok().and_then(|it| symbols.name(it));
+
+        let operands = self
+            .operands
+            .iter()
+            .enumerate()
+            .map(|(idx, &operand)| {
+                match operand {
+                    Operand::Position(addr) => name(addr).map(|it| format!("[{it}]")),
+                    Operand::Immediate(target) if is_jump && idx == 1 => name(target),
+                    _ => None,
+                }
+                .unwrap_or_else(|| operand.to_string())
+            })
+            .join(", ");
+
+        if operands.is_empty() {
+            self.mnemonic.to_owned()
+        } else {
+            format!("{} {operands}", self.mnemonic)
+        }
+    }
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.annotate(&SymbolTable::default()))
+    }
+}
+
+// One line per instruction. Cells that don't decode are listed as `data`, which also
+// keeps the listing in sync after inline data.
+#[must_use]
+pub fn disassemble(mem: &[i64], range: Range<usize>) -> String {
+    disassemble_with(mem, range, &SymbolTable::default())
+}
+
+// Like `disassemble`, with a label line wherever a symbol starts. Variables are listed as
+// data and string tables one string per line, without trying to decode them.
+#[must_use]
+pub fn disassemble_with(mem: &[i64], range: Range<usize>, symbols: &SymbolTable) -> String {
+    let mut result = String::new();
+    let end = range.end.min(mem.len());
+    let mut addr = range.start;
+
+    while addr < end {
+        for symbol in symbols.starting_at(addr) {
+            let _ = writeln!(result, "{}:", symbol.name);
+        }
+
+        let symbol = symbols
+            .at(addr)
+            .filter(|it| it.kind != SymbolKind::Function);
+        let string_len = usize::try_from(mem[addr])
+            .ok()
+            .filter(|len| symbol.is_some_and(|it| addr + len < it.range.end.min(end)));
+        let instruction = symbol
+            .is_none()
+            .then(|| Instruction::decode(mem, addr))
+            .flatten();
+
+        match (symbol.map(|it| it.kind), string_len, instruction) {
+            (Some(SymbolKind::StringTable), Some(len), _) => {
+                let text = mem[addr + 1..=addr + len]
+                    .iter()
+                    .map(|&it| {
+                        u8::try_from(it)
+                            .ok()
+                            .filter(u8::is_ascii)
+                            .map_or('?', char::from)
+                    })
+                    .collect::<String>();
+
+                let _ = writeln!(result, "{addr:>5}: string {text:?}");
+                addr += len + 1;
+            }
+            (_, _, Some(instruction)) => {
+                let text = instruction.annotate(symbols);
+                let _ = writeln!(result, "{addr:>5}: {text}");
+                addr += instruction.size();
+            }
+            _ => {
+                let _ = writeln!(result, "{addr:>5}: data {}", mem[addr]);
+                addr += 1;
+            }
+        }
+    }
+
+    result
+}
+
+impl IntCode {
+    #[must_use]
+    pub fn current_instruction(&self) -> Option<Instruction> {
+        Instruction::decode(&self.mem, self.vpc)
+    }
+
+    #[must_use]
+    pub fn disassemble(&self, range: Range<usize>) -> String {
+        disassemble(&self.mem, range)
+    }
+
+    #[must_use]
+    pub fn disassemble_with(&self, range: Range<usize>, symbols: &SymbolTable) -> String {
+        disassemble_with(&self.mem, range, symbols)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_disassemble() {
+        let mem = [1002, 4, 3, 4, 33, 109, -5, 21107, 1, 2, -3, 99];
+
+        assert_eq!(
+            disassemble(&mem, 0..mem.len()),
+            "    0: mul [4], 3, [4]
+    4: data 33
+    5: arb -5
+    7: lt 1, 2, [rb-3]
+   11: halt
+"
+        );
+    }
+
+    #[test]
+    fn test_symbols() {
+        let mem = [1105, 1, 7, 2, 72, 105, 99, 1001, 3, 1, 3, 99];
+        let symbols =
+            SymbolTable::parse("function 7 increment\nstrings 3..6 greeting\nvariable 6 counter")
+                .expect("valid symbols");
+
+        assert_eq!(
+            disassemble_with(&mem, 0..mem.len(), &symbols),
+            "    0: jt 1, increment
+greeting:
+    3: string \"Hi\"
+counter:
+    6: data 99
+increment:
+    7: add [greeting], 1, [greeting]
+   11: halt
+"
+        );
+    }
+
+    #[test]
+    fn test_current_instruction() {
+        let vm = IntCode::new(&[204, 7, 99]);
+
+        assert_eq!(
+            vm.current_instruction().map(|it| it.to_string()),
+            Some(String::from("out [rb+7]"))
+        );
+    }
+}
@@ -0,0 +1,137 @@
+use std::convert::{Infallible, TryFrom};
+use std::fmt;
+
+use crate::intcode::device::Machine;
+use crate::intcode::{IntCode, State};
+
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub enum Frame<T> {
+    Data(T),
+    Waiting,
+    Halted,
+}
+
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub enum FrameError<E = Infallible> {
+    // The VM halted after writing only part of a frame.
+    Truncated(Vec<i64>),
+    // The VM asked for input after writing only part of a frame.
+    Interrupted(Vec<i64>),
+    // A complete frame was rejected by the `TryFrom` conversion.
+    Invalid(E),
+}
+
+impl<E: fmt::Display> fmt::Display for FrameError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Truncated(partial) => write!(f, "VM halted mid-frame after {partial:?}"),
+            Self::Interrupted(partial) => {
+                write!(f, "VM waited for input mid-frame after {partial:?}")
+            }
+            Self::Invalid(e) => write!(f, "invalid frame: {e}"),
+        }
+    }
+}
+
+impl<E: fmt::Debug + fmt::Display> std::error::Error for FrameError<E> {}
+
+impl IntCode {
+    /// # Errors
+    ///
+    /// Returns `FrameError::Truncated` or `FrameError::Interrupted` if the VM stops after
+    /// writing only part of a frame.
+    pub fn next_frame<const N: usize>(&mut self) -> Result<Frame<[i64; N]>, FrameError> {
+        read_frame(self)
+    }
+
+    /// # Errors
+    ///
+    /// Fails like `next_frame`, and with `FrameError::Invalid` if the conversion rejects a
+    /// complete frame.
+    pub fn next_frame_as<T, const N: usize>(&mut self) -> Result<Frame<T>, FrameError<T::Error>>
+    where
+        T: TryFrom<[i64; N]>,
+    {
+        read_frame(self)
+    }
+}
+
+/// Reads the next `N` outputs of any machine as one frame, see `IntCode::next_frame_as`.
+///
+/// # Errors
+///
+/// Fails like `IntCode::next_frame_as`.
+pub fn read_frame<M, T, const N: usize>(machine: &mut M) -> Result<Frame<T>, FrameError<T::Error>>
+where
+    M: Machine + ?Sized,
+    T: TryFrom<[i64; N]>,
+{
+    let mut frame = [0; N];
+
+    for idx in 0..N {
+        match machine.run() {
+            State::Write(n) => frame[idx] = n,
+            State::Waiting if idx == 0 => return Ok(Frame::Waiting),
+            State::Halted(_) if idx == 0 => return Ok(Frame::Halted),
+            State::Waiting => return Err(FrameError::Interrupted(frame[..idx].to_vec())),
+            State::Halted(_) => return Err(FrameError::Truncated(frame[..idx].to_vec())),
+        }
+    }
+
+    T::try_from(frame)
+        .map(Frame::Data)
+        .map_err(FrameError::Invalid)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(PartialEq, Debug)]
+    struct Pair(i64, i64);
+
+    impl TryFrom<[i64; 2]> for Pair {
+        type Error = i64;
+
+        fn try_from([lhs, rhs]: [i64; 2]) -> Result<Self, Self::Error> {
+            if lhs < 0 {
+                return Err(lhs);
+            }
+
+            Ok(Self(lhs, rhs))
+        }
+    }
+
+    #[test]
+    fn test_frames() {
+        let mut vm = IntCode::new(&[104, 1, 104, 2, 3, 0, 104, 3, 104, 4, 99]);
+
+        assert_eq!(vm.next_frame::<2>(), Ok(Frame::Data([1, 2])));
+        assert_eq!(vm.next_frame::<2>(), Ok(Frame::Waiting));
+        vm.input(0);
+        assert_eq!(vm.next_frame::<2>(), Ok(Frame::Data([3, 4])));
+        assert_eq!(vm.next_frame::<2>(), Ok(Frame::Halted));
+    }
+
+    #[test]
+    fn test_truncated() {
+        let mut vm = IntCode::new(&[104, 1, 104, 2, 99]);
+
+        assert_eq!(vm.next_frame::<3>(), Err(FrameError::Truncated(vec![1, 2])));
+    }
+
+    #[test]
+    fn test_interrupted() {
+        let mut vm = IntCode::new(&[104, 1, 3, 0, 99]);
+
+        assert_eq!(vm.next_frame::<2>(), Err(FrameError::Interrupted(vec![1])));
+    }
+
+    #[test]
+    fn test_try_from() {
+        let mut vm = IntCode::new(&[104, 1, 104, 2, 104, -1, 104, 0, 99]);
+
+        assert_eq!(vm.next_frame_as::<Pair, 2>(), Ok(Frame::Data(Pair(1, 2))));
+        assert_eq!(vm.next_frame_as::<Pair, 2>(), Err(FrameError::Invalid(-1)));
+    }
+}
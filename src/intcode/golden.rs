@@ -0,0 +1,288 @@
+use std::fmt;
+use std::path::Path;
+
+use itertools::Itertools;
+
+use crate::intcode::lines::{lines, LineError};
+use crate::intcode::patch::{Patch, PatchError, Preset};
+use crate::intcode::program::Program;
+use crate::intcode::{IntCode, State};
+
+// Programs that don't halt within this many instructions fail instead of hanging the test run.
+const MAX_INSTRUCTIONS: u64 = 10_000_000;
+
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct GoldenTest {
+    pub program: Program,
+    pub patches: Vec<Patch>,
+    pub inputs: Vec<i64>,
+    pub outputs: Vec<i64>,
+    // Expected cells of the final memory, as runs of values starting at an address.
+    pub mem: Vec<(usize, Vec<i64>)>,
+}
+
+pub type GoldenError = LineError;
+
+#[derive(Debug)]
+pub enum Failure {
+    Patch(PatchError),
+    NoInput {
+        consumed: usize,
+    },
+    UnusedInput {
+        consumed: usize,
+    },
+    Timeout,
+    Output {
+        expected: Vec<i64>,
+        found: Vec<i64>,
+    },
+    Memory {
+        addr: usize,
+        expected: i64,
+        found: i64,
+    },
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Patch(e) => write!(f, "{e}"),
+            Self::NoInput { consumed } => {
+                write!(f, "program asked for more than {consumed} inputs")
+            }
+            Self::UnusedInput { consumed } => {
+                write!(f, "program halted after reading only {consumed} inputs")
+            }
+            Self::Timeout => write!(
+                f,
+                "program didn't halt within {MAX_INSTRUCTIONS} instructions"
+            ),
+            Self::Output { expected, found } => write!(
+                f,
+                "expected output {}, found {}",
+                expected.iter().join(","),
+                found.iter().join(",")
+            ),
+            Self::Memory {
+                addr,
+                expected,
+                found,
+            } => write!(f, "expected {expected} at address {addr}, found {found}"),
+        }
+    }
+}
+
+impl std::error::Error for Failure {}
+
+fn parse_values(inp: &str) -> Option<Vec<i64>> {
+    inp.split(',').map(|it| it.trim().parse().ok()).collect()
+}
+
+impl GoldenTest {
+    /// One `key value` pair per line, everything after a `#` is a comment:
+    ///
+    /// ```text
+    /// program 1,0,0,0,99    the program, exactly once
+    /// patch 1: 0 -> 12      applied before running, same syntax as patch files
+    /// input 1,2             values fed to the program, in order
+    /// output 3,4            values the program must write, and nothing else
+    /// mem 0: 2,0,0,0        memory after the program halted, starting at an address
+    /// ```
+    ///
+    /// `input`, `output` and `patch` may be repeated, later lines are appended.
+    ///
+    /// # Errors
+    ///
+    /// Returns `GoldenError::Parse` for the first invalid line and `GoldenError::Missing`
+    /// without a `program` line.
+    pub fn parse(inp: &str) -> Result<Self, GoldenError> {
+        let mut program = None;
+        let mut test = Self {
+            program: Program::default(),
+            patches: Vec::new(),
+            inputs: Vec::new(),
+            outputs: Vec::new(),
+            mem: Vec::new(),
+        };
+
+        for line in lines(inp) {
+            let error = || line.error();
+            let (key, value) = line.key_value();
+            if value.is_empty() {
+                return Err(error());
+            }
+
+            match key {
+                "program" if program.is_none() => {
+                    program = Some(Program::parse(value).map_err(|_| error())?);
+                }
+                "patch" => test.patches.push(Patch::parse(value).ok_or_else(error)?),
+                "input" => test.inputs.extend(parse_values(value).ok_or_else(error)?),
+                "output" => test.outputs.extend(parse_values(value).ok_or_else(error)?),
+                "mem" => {
+                    let (addr, values) = value.split_once(':').ok_or_else(error)?;
+                    let addr = addr.trim().parse().map_err(|_| error())?;
+                    test.mem
+                        .push((addr, parse_values(values).ok_or_else(error)?));
+                }
+                _ => return Err(error()),
+            }
+        }
+
+        test.program = program.ok_or(GoldenError::Missing("program"))?;
+        Ok(test)
+    }
+
+    /// # Errors
+    ///
+    /// Returns `GoldenError::Io` if the file can't be read, and fails like `parse` otherwise.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, GoldenError> {
+        Self::parse(&std::fs::read_to_string(path)?)
+    }
+
+    /// Runs the program to completion. It has to halt having read exactly the given inputs.
+    ///
+    /// # Errors
+    ///
+    /// Returns the first `Failure` found: a patch that doesn't apply, a mismatch in inputs,
+    /// outputs or memory, or a program that doesn't halt in time.
+    pub fn run(&self) -> Result<(), Failure> {
+        let mut vm = IntCode::new(&self.program);
+
+        let preset = Preset {
+            name: String::from("golden test"),
+            patches: self.patches.clone(),
+        };
+        preset.apply(&mut vm).map_err(Failure::Patch)?;
+
+        let mut inputs = self.inputs.iter();
+        let mut outputs = Vec::new();
+
+        loop {
+            if vm.stats().instructions >= MAX_INSTRUCTIONS {
+                return Err(Failure::Timeout);
+            }
+
+            match vm.step() {
+                Some(State::Waiting) => match inputs.next() {
+                    Some(&val) => vm.input(val),
+                    None => {
+                        return Err(Failure::NoInput {
+                            consumed: self.inputs.len(),
+                        })
+                    }
+                },
+                Some(State::Write(val)) => outputs.push(val),
+                Some(State::Halted(_)) => break,
+                None => {}
+            }
+        }
+
+        if inputs.len() > 0 {
+            return Err(Failure::UnusedInput {
+                consumed: self.inputs.len() - inputs.len(),
+            });
+        }
+
+        if outputs != self.outputs {
+            return Err(Failure::Output {
+                expected: self.outputs.clone(),
+                found: outputs,
+            });
+        }
+
+        for (start, values) in &self.mem {
+            for (addr, &expected) in (*start..).zip(values) {
+                let found = vm.mem.get(addr).copied().unwrap_or_default();
+                if found != expected {
+                    return Err(Failure::Memory {
+                        addr,
+                        expected,
+                        found,
+                    });
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let test = GoldenTest::parse(
+            "# comment\nprogram 3,0,4,0,99\npatch 4: 99 -> 99\ninput 7\noutput 7\nmem 0: 7, 0",
+        )
+        .expect("valid golden test");
+
+        assert_eq!(&*test.program, &[3, 0, 4, 0, 99]);
+        assert_eq!(test.inputs, vec![7]);
+        assert_eq!(test.mem, vec![(0, vec![7, 0])]);
+        assert!(test.run().is_ok());
+
+        assert!(matches!(
+            GoldenTest::parse("input 1"),
+            Err(GoldenError::Missing("program"))
+        ));
+        assert!(matches!(
+            GoldenTest::parse("program 99\noutput x"),
+            Err(GoldenError::Parse { line: 2, .. })
+        ));
+    }
+
+    #[test]
+    fn test_failures() {
+        let parse = |inp| GoldenTest::parse(inp).expect("valid golden test");
+
+        // A program that never writes doesn't pass a test expecting output.
+        assert!(matches!(
+            parse("program 99\noutput 1").run(),
+            Err(Failure::Output { .. })
+        ));
+        assert!(matches!(
+            parse("program 3,0,99").run(),
+            Err(Failure::NoInput { consumed: 0 })
+        ));
+        assert!(matches!(
+            parse("program 3,0,99\ninput 1,2").run(),
+            Err(Failure::UnusedInput { consumed: 1 })
+        ));
+        assert!(matches!(
+            parse("program 1105,1,0").run(),
+            Err(Failure::Timeout)
+        ));
+        assert!(matches!(
+            parse("program 99\nmem 0: 98").run(),
+            Err(Failure::Memory { addr: 0, .. })
+        ));
+    }
+
+    #[test]
+    fn test_golden_files() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("golden");
+        let mut paths = std::fs::read_dir(&dir)
+            .expect("golden test directory")
+            .map(|it| it.expect("directory entry").path())
+            .filter(|it| it.extension().is_some_and(|ext| ext == "golden"))
+            .collect::<Vec<_>>();
+        paths.sort();
+        assert!(!paths.is_empty());
+
+        let failures = paths
+            .iter()
+            .filter_map(|path| {
+                let result = GoldenTest::from_file(path)
+                    .map_err(|e| e.to_string())
+                    .and_then(|test| test.run().map_err(|e| e.to_string()));
+                result.err().map(|e| format!("{}: {}", path.display(), e))
+            })
+            .collect::<Vec<_>>();
+
+        assert!(failures.is_empty(), "{}", failures.join("\n"));
+    }
+}
@@ -0,0 +1,89 @@
+use std::fmt;
+use std::io;
+
+// Shared by the line based text formats: patch files, symbol files, golden tests and
+// snapshots.
+#[derive(Debug)]
+pub enum LineError {
+    Io(io::Error),
+    Parse { line: usize, text: String },
+    Missing(&'static str),
+}
+
+impl fmt::Display for LineError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "could not access file: {e}"),
+            Self::Parse { line, text } => write!(f, "invalid line {line}: {text:?}"),
+            Self::Missing(key) => write!(f, "no {key:?} entry"),
+        }
+    }
+}
+
+impl std::error::Error for LineError {}
+
+impl From<io::Error> for LineError {
+    fn from(e: io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub struct Line<'a> {
+    // Counting from 1.
+    pub number: usize,
+    pub raw: &'a str,
+    // `raw` without its comment and surrounding whitespace.
+    pub text: &'a str,
+}
+
+impl<'a> Line<'a> {
+    #[must_use]
+    pub fn error(&self) -> LineError {
+        LineError::Parse {
+            line: self.number,
+            text: self.raw.to_owned(),
+        }
+    }
+
+    // `key value`, with an empty value if there is no space.
+    #[must_use]
+    pub fn key_value(&self) -> (&'a str, &'a str) {
+        let text = self.text;
+        let (key, value) = text.split_once(' ').unwrap_or((text, ""));
+        (key, value.trim())
+    }
+}
+
+// Lines that aren't empty once everything after a `#` is stripped.
+pub fn lines(inp: &str) -> impl Iterator<Item = Line<'_>> {
+    inp.lines()
+        .enumerate()
+        .map(|(idx, raw)| Line {
+            number: idx + 1,
+            raw,
+            text: raw.split('#').next().unwrap_or_default().trim(),
+        })
+        .filter(|it| !it.text.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lines() {
+        let found = lines("# header\n\nvpc 2  # comment\n  mem\n")
+            .map(|it| (it.number, it.text, it.key_value()))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            found,
+            vec![(3, "vpc 2", ("vpc", "2")), (4, "mem", ("mem", ""))]
+        );
+        assert!(matches!(
+            lines("a\nb").nth(1).map(|it| it.error()),
+            Some(LineError::Parse { line: 2, .. })
+        ));
+    }
+}
@@ -0,0 +1,233 @@
+use std::fmt::{self, Write};
+use std::ops::Range;
+
+use itertools::{EitherOrBoth, Itertools};
+
+use crate::intcode::symbols::SymbolTable;
+use crate::intcode::IntCode;
+
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub enum Radix {
+    Decimal,
+    Hex,
+}
+
+impl Radix {
+    fn format(self, val: i64) -> String {
+        match self {
+            Self::Decimal => val.to_string(),
+            Self::Hex if val < 0 => format!("-{:x}", val.unsigned_abs()),
+            Self::Hex => format!("{val:x}"),
+        }
+    }
+
+    fn format_unsigned(self, val: usize) -> String {
+        match self {
+            Self::Decimal => val.to_string(),
+            Self::Hex => format!("{val:x}"),
+        }
+    }
+
+    fn format_addr(self, addr: usize) -> String {
+        match self {
+            Self::Decimal => format!("{addr:>5}"),
+            Self::Hex => format!("{addr:04x}"),
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub struct CellChange {
+    pub addr: usize,
+    pub before: i64,
+    pub after: i64,
+}
+
+#[derive(PartialEq, Eq, Clone, Debug, Default)]
+pub struct SnapshotDiff {
+    pub vpc: Option<(usize, usize)>,
+    pub rel_base: Option<(i64, i64)>,
+    pub cells: Vec<CellChange>,
+}
+
+impl SnapshotDiff {
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.vpc.is_none() && self.rel_base.is_none() && self.cells.is_empty()
+    }
+}
+
+impl SnapshotDiff {
+    // Same as `Display`, with changed cells named after `symbols` where known.
+    #[must_use]
+    pub fn annotate(&self, symbols: &SymbolTable) -> String {
+        let mut result = String::new();
+
+        if let Some((before, after)) = self.vpc {
+            let _ = writeln!(result, "vpc: {before} -> {after}");
+        }
+
+        if let Some((before, after)) = self.rel_base {
+            let _ = writeln!(result, "rel_base: {before} -> {after}");
+        }
+
+        for change in &self.cells {
+            let _ = writeln!(
+                result,
+                "[{}] {} -> {}",
+                symbols.label(change.addr),
+                change.before,
+                change.after
+            );
+        }
+
+        result
+    }
+}
+
+impl fmt::Display for SnapshotDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.annotate(&SymbolTable::default()))
+    }
+}
+
+impl IntCode {
+    // Renders `range` as rows of `width` cells, each prefixed by the addresses it covers.
+    #[must_use]
+    pub fn dump(&self, range: Range<usize>, radix: Radix, width: usize) -> String {
+        self.dump_with(range, radix, width, &SymbolTable::default())
+    }
+
+    // Same as `dump`, each row followed by the symbols starting in it.
+    #[must_use]
+    pub fn dump_with(
+        &self,
+        range: Range<usize>,
+        radix: Radix,
+        width: usize,
+        symbols: &SymbolTable,
+    ) -> String {
+        let end = range.end.min(self.mem.len());
+        let start = range.start.min(end);
+        let width = width.max(1);
+
+        let cells = self.mem[start..end]
+            .iter()
+            .map(|it| radix.format(*it))
+            .collect_vec();
+        let cell_width = cells.iter().map(String::len).max().unwrap_or(0);
+
+        let mut result = String::new();
+
+        for (row_idx, row) in cells.chunks(width).enumerate() {
+            let row_start = start + row_idx * width;
+            let row_end = row_start + row.len() - 1;
+
+            result.push_str(&radix.format_addr(row_start));
+            result.push('-');
+            result.push_str(&radix.format_addr(row_end));
+            result.push_str(" |");
+
+            for cell in row {
+                let _ = write!(result, " {cell:>cell_width$}");
+            }
+
+            let names = (row_start..=row_end)
+                .flat_map(|addr| symbols.starting_at(addr))
+                .map(|it| format!("{}@{}", it.name, radix.format_unsigned(it.range.start)))
+                .join(", ");
+            if !names.is_empty() {
+                result.push_str(" ; ");
+                result.push_str(&names);
+            }
+
+            result.push('\n');
+        }
+
+        result
+    }
+
+    #[must_use]
+    pub fn diff(&self, other: &Self) -> SnapshotDiff {
+        let cells = self
+            .mem
+            .iter()
+            .zip_longest(&other.mem)
+            .enumerate()
+            .filter_map(|(addr, it)| {
+                let (before, after) = match it {
+                    EitherOrBoth::Both(l, r) => (*l, *r),
+                    EitherOrBoth::Left(l) => (*l, 0),
+                    EitherOrBoth::Right(r) => (0, *r),
+                };
+
+                if before == after {
+                    None
+                } else {
+                    Some(CellChange {
+                        addr,
+                        before,
+                        after,
+                    })
+                }
+            })
+            .collect();
+
+        SnapshotDiff {
+            vpc: Some((self.vpc, other.vpc)).filter(|(l, r)| l != r),
+            rel_base: Some((self.rel_base, other.rel_base)).filter(|(l, r)| l != r),
+            cells,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dump() {
+        let vm = IntCode::new(&[1, 0, 0, 3, 99, -17]);
+
+        assert_eq!(
+            vm.dump(0..6, Radix::Decimal, 4),
+            "    0-    3 |   1   0   0   3\n    4-    5 |  99 -17\n"
+        );
+        assert_eq!(
+            vm.dump(2..6, Radix::Hex, 8),
+            "0002-0005 |   0   3  63 -11\n"
+        );
+
+        let symbols = SymbolTable::parse("function 0 main\nvariable 5 offset\nvariable 3 result")
+            .expect("valid symbols");
+        assert_eq!(
+            vm.dump_with(0..6, Radix::Decimal, 4, &symbols),
+            "    0-    3 |   1   0   0   3 ; main@0, result@3\n    4-    5 |  99 -17 ; offset@5\n"
+        );
+    }
+
+    #[test]
+    fn test_diff() {
+        let before = IntCode::new(&[1, 0, 0, 3, 99]);
+        let mut after = before.clone();
+        after.run();
+
+        let diff = before.diff(&after);
+
+        assert_eq!(diff.vpc, Some((0, 4)));
+        assert_eq!(diff.rel_base, None);
+        assert_eq!(
+            diff.cells,
+            vec![CellChange {
+                addr: 3,
+                before: 3,
+                after: 2
+            }]
+        );
+        assert_eq!(diff.to_string(), "vpc: 0 -> 4\n[3] 3 -> 2\n");
+
+        let symbols = SymbolTable::parse("variable 3 result").expect("valid symbols");
+        assert_eq!(diff.annotate(&symbols), "vpc: 0 -> 4\n[result] 3 -> 2\n");
+        assert!(after.diff(&after).is_empty());
+    }
+}
@@ -0,0 +1,163 @@
+use std::num::NonZeroUsize;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+use crate::intcode::{IntCode, State};
+
+const BATCH_SIZE: usize = 64;
+
+fn num_workers(len: usize) -> usize {
+    let cores = thread::available_parallelism().map_or(1, NonZeroUsize::get);
+    cores.min(len / BATCH_SIZE + 1)
+}
+
+/// Calls `f` on every item on all available cores, results are in the order of `items`.
+///
+/// # Panics
+///
+/// Panics if `f` panics on any item, after all workers have stopped.
+pub fn par_map<T, R, F>(items: &[T], f: F) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+    F: Fn(&T) -> R + Sync,
+{
+    let next = AtomicUsize::new(0);
+    let results = Mutex::new(Vec::with_capacity(items.len()));
+
+    thread::scope(|s| {
+        for _ in 0..num_workers(items.len()) {
+            s.spawn(|| loop {
+                let start = next.fetch_add(BATCH_SIZE, Ordering::Relaxed);
+                if start >= items.len() {
+                    break;
+                }
+
+                let end = (start + BATCH_SIZE).min(items.len());
+                let batch = items[start..end].iter().map(&f).collect::<Vec<_>>();
+
+                results
+                    .lock()
+                    .expect("Worker panicked while holding the lock")
+                    .push((start, batch));
+            });
+        }
+    });
+
+    let mut results = results
+        .into_inner()
+        .expect("Worker panicked while holding the lock");
+    results.sort_unstable_by_key(|(start, _)| *start);
+    results.into_iter().flat_map(|(_, batch)| batch).collect()
+}
+
+/// Returns the first item (in the order of `items`) for which `f` returns `Some`. Workers
+/// stop as soon as no unchecked item can come before the best match found so far.
+///
+/// # Panics
+///
+/// Panics if `f` panics on any item, after all workers have stopped.
+pub fn par_find<T, R, F>(items: &[T], f: F) -> Option<(usize, R)>
+where
+    T: Sync,
+    R: Send,
+    F: Fn(&T) -> Option<R> + Sync,
+{
+    let next = AtomicUsize::new(0);
+    let best = Mutex::new(None);
+    let best_idx = AtomicUsize::new(usize::MAX);
+
+    thread::scope(|s| {
+        for _ in 0..num_workers(items.len()) {
+            s.spawn(|| loop {
+                let start = next.fetch_add(BATCH_SIZE, Ordering::Relaxed);
+                if start >= items.len() || start > best_idx.load(Ordering::Relaxed) {
+                    break;
+                }
+
+                let end = (start + BATCH_SIZE).min(items.len());
+                let found = (start..end).find_map(|idx| f(&items[idx]).map(|it| (idx, it)));
+
+                if let Some((idx, res)) = found {
+                    let mut best = best.lock().expect("Worker panicked while holding the lock");
+                    if best_idx.fetch_min(idx, Ordering::Relaxed) > idx {
+                        *best = Some((idx, res));
+                    }
+                }
+            });
+        }
+    });
+
+    best.into_inner()
+        .expect("Worker panicked while holding the lock")
+}
+
+// Runs a fresh VM on `inputs` and collects its outputs until it halts or runs out of input.
+#[must_use]
+pub fn run_pure(program: &[i64], inputs: &[i64]) -> Vec<i64> {
+    let mut vm = IntCode::new(program);
+    let mut inputs = inputs.iter();
+    let mut outputs = Vec::new();
+
+    loop {
+        match vm.run() {
+            State::Waiting => match inputs.next() {
+                Some(inp) => vm.input(*inp),
+                None => return outputs,
+            },
+            State::Write(n) => outputs.push(n),
+            State::Halted(_) => return outputs,
+        }
+    }
+}
+
+#[must_use]
+pub fn map_inputs(program: &[i64], inputs: &[Vec<i64>]) -> Vec<Vec<i64>> {
+    par_map(inputs, |inp| run_pure(program, inp))
+}
+
+pub fn find_inputs<P>(program: &[i64], inputs: &[Vec<i64>], pred: P) -> Option<usize>
+where
+    P: Fn(&[i64]) -> bool + Sync,
+{
+    par_find(inputs, |inp| pred(&run_pure(program, inp)).then_some(())).map(|(idx, ())| idx)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Outputs the sum of two inputs.
+    const SUM: [i64; 14] = [3, 11, 3, 12, 1, 11, 12, 13, 4, 13, 99, 0, 0, 0];
+
+    #[test]
+    fn test_par_map() {
+        let items = (0..1000).collect::<Vec<i64>>();
+        let squares = par_map(&items, |it| it * it);
+
+        assert_eq!(squares, items.iter().map(|it| it * it).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_par_find() {
+        let items = (0..10_000).collect::<Vec<i64>>();
+
+        assert_eq!(
+            par_find(&items, |it| (it % 1234 == 1233).then_some(*it)),
+            Some((1233, 1233))
+        );
+        assert_eq!(par_find(&items, |_| None::<()>), None);
+    }
+
+    #[test]
+    fn test_map_inputs() {
+        let inputs = vec![vec![1, 2], vec![3, 4], vec![5]];
+
+        assert_eq!(
+            map_inputs(&SUM, &inputs),
+            vec![vec![3], vec![7], Vec::new()]
+        );
+        assert_eq!(find_inputs(&SUM, &inputs, |out| out == [7]), Some(1));
+    }
+}
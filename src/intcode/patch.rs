@@ -0,0 +1,285 @@
+use std::fmt;
+use std::path::Path;
+
+use crate::intcode::lines::{lines, LineError};
+use crate::intcode::IntCode;
+
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub struct Patch {
+    pub addr: usize,
+    pub expected: Option<i64>,
+    pub value: i64,
+}
+
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct Preset {
+    pub name: String,
+    pub patches: Vec<Patch>,
+}
+
+#[derive(PartialEq, Eq, Clone, Debug, Default)]
+pub struct PatchSet {
+    presets: Vec<Preset>,
+}
+
+#[derive(Debug)]
+pub enum PatchError {
+    File(LineError),
+    UnknownPreset(String),
+    OutOfRange {
+        preset: String,
+        addr: usize,
+    },
+    Mismatch {
+        preset: String,
+        addr: usize,
+        expected: i64,
+        found: i64,
+    },
+}
+
+impl fmt::Display for PatchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::File(e) => write!(f, "patch file: {e}"),
+            Self::UnknownPreset(name) => write!(f, "unknown preset {name:?}"),
+            Self::OutOfRange { preset, addr } => {
+                write!(
+                    f,
+                    "preset {preset:?} patches address {addr} outside of memory"
+                )
+            }
+            Self::Mismatch {
+                preset,
+                addr,
+                expected,
+                found,
+            } => write!(
+                f,
+                "preset {preset:?} expects {expected} at address {addr}, found {found}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for PatchError {}
+
+impl From<LineError> for PatchError {
+    fn from(e: LineError) -> Self {
+        Self::File(e)
+    }
+}
+
+impl fmt::Display for Patch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.expected {
+            Some(expected) => write!(f, "{}: {} -> {}", self.addr, expected, self.value),
+            None => write!(f, "{}: {}", self.addr, self.value),
+        }
+    }
+}
+
+impl Patch {
+    // `addr: value`, or `addr: old -> new` to check the old value first.
+    #[must_use]
+    pub fn parse(line: &str) -> Option<Self> {
+        let (addr, values) = line.split_once(':')?;
+        let addr = addr.trim().parse().ok()?;
+
+        let (expected, value) = match values.split_once("->") {
+            Some((expected, value)) => (Some(expected.trim().parse().ok()?), value),
+            None => (None, values),
+        };
+
+        Some(Self {
+            addr,
+            expected,
+            value: value.trim().parse().ok()?,
+        })
+    }
+}
+
+impl Preset {
+    /// Verifies every patch against the current memory without modifying it.
+    ///
+    /// # Errors
+    ///
+    /// Returns `PatchError::OutOfRange` for a patch outside of memory and
+    /// `PatchError::Mismatch` if a cell doesn't hold the value a patch expects.
+    pub fn check(&self, vm: &IntCode) -> Result<(), PatchError> {
+        for patch in &self.patches {
+            let found = *vm
+                .mem
+                .get(patch.addr)
+                .ok_or_else(|| PatchError::OutOfRange {
+                    preset: self.name.clone(),
+                    addr: patch.addr,
+                })?;
+
+            match patch.expected {
+                Some(expected) if expected != found => {
+                    return Err(PatchError::Mismatch {
+                        preset: self.name.clone(),
+                        addr: patch.addr,
+                        expected,
+                        found,
+                    })
+                }
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Applies either all patches or, if any of them doesn't match, none.
+    ///
+    /// # Errors
+    ///
+    /// Fails like `check`, in which case memory is left untouched.
+    pub fn apply(&self, vm: &mut IntCode) -> Result<(), PatchError> {
+        self.check(vm)?;
+
+        for patch in &self.patches {
+            vm.init_ram(patch.addr, patch.value);
+        }
+
+        Ok(())
+    }
+}
+
+impl PatchSet {
+    /// Presets start with a `[name]` header, followed by `addr: value` or `addr: old -> new`
+    /// lines. Everything after a `#` is a comment.
+    ///
+    /// # Errors
+    ///
+    /// Returns `PatchError::File` for a line that isn't a header or a patch, or a patch
+    /// before the first header.
+    pub fn parse(inp: &str) -> Result<Self, PatchError> {
+        let mut presets: Vec<Preset> = Vec::new();
+
+        for line in lines(inp) {
+            let error = || PatchError::File(line.error());
+
+            if let Some(name) = line
+                .text
+                .strip_prefix('[')
+                .and_then(|it| it.strip_suffix(']'))
+            {
+                presets.push(Preset {
+                    name: name.trim().to_owned(),
+                    patches: Vec::new(),
+                });
+            } else {
+                let patch = Patch::parse(line.text).ok_or_else(error)?;
+                presets.last_mut().ok_or_else(error)?.patches.push(patch);
+            }
+        }
+
+        Ok(Self { presets })
+    }
+
+    /// # Errors
+    ///
+    /// Returns `PatchError::File` if the file can't be read or parsed.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, PatchError> {
+        Self::parse(&std::fs::read_to_string(path).map_err(LineError::from)?)
+    }
+
+    #[must_use]
+    pub fn presets(&self) -> &[Preset] {
+        &self.presets
+    }
+
+    /// # Errors
+    ///
+    /// Returns `PatchError::UnknownPreset` if there is no preset called `name`.
+    pub fn preset(&self, name: &str) -> Result<&Preset, PatchError> {
+        self.presets
+            .iter()
+            .find(|it| it.name == name)
+            .ok_or_else(|| PatchError::UnknownPreset(name.to_owned()))
+    }
+
+    /// # Errors
+    ///
+    /// Fails like `preset` and `Preset::apply`.
+    pub fn apply(&self, name: &str, vm: &mut IntCode) -> Result<(), PatchError> {
+        self.preset(name)?.apply(vm)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PATCHES: &str = "\
+# comment
+[free play]
+0: 1 -> 2   # insert coins
+
+[poke]
+3: 7
+";
+
+    #[test]
+    fn test_parse() {
+        let set = PatchSet::parse(PATCHES).expect("valid patch file");
+
+        assert_eq!(
+            set.presets(),
+            &[
+                Preset {
+                    name: String::from("free play"),
+                    patches: vec![Patch {
+                        addr: 0,
+                        expected: Some(1),
+                        value: 2
+                    }],
+                },
+                Preset {
+                    name: String::from("poke"),
+                    patches: vec![Patch {
+                        addr: 3,
+                        expected: None,
+                        value: 7
+                    }],
+                },
+            ]
+        );
+
+        assert!(matches!(
+            PatchSet::parse("0: 1"),
+            Err(PatchError::File(LineError::Parse { line: 1, .. }))
+        ));
+        assert!(matches!(
+            PatchSet::parse("[a]\n0 -> 1"),
+            Err(PatchError::File(LineError::Parse { line: 2, .. }))
+        ));
+    }
+
+    #[test]
+    fn test_apply() {
+        let set = PatchSet::parse(PATCHES).expect("valid patch file");
+
+        let mut vm = IntCode::new(&[1, 0, 0, 0, 99]);
+        assert!(set.apply("free play", &mut vm).is_ok());
+        assert!(set.apply("poke", &mut vm).is_ok());
+        assert_eq!(&vm.mem[..5], &[2, 0, 0, 7, 99]);
+
+        assert!(matches!(
+            set.apply("free play", &mut vm),
+            Err(PatchError::Mismatch {
+                addr: 0,
+                expected: 1,
+                found: 2,
+                ..
+            })
+        ));
+        assert!(matches!(
+            set.apply("pay to win", &mut vm),
+            Err(PatchError::UnknownPreset(_))
+        ));
+    }
+}
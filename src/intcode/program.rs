@@ -0,0 +1,162 @@
+use std::borrow::Borrow;
+use std::fmt;
+use std::io;
+use std::ops::Deref;
+use std::path::Path;
+use std::str::FromStr;
+
+#[derive(PartialEq, Eq, Hash, Clone, Debug, Default)]
+pub struct Program(Vec<i64>);
+
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct ParseError {
+    pub index: usize,
+    pub token: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "invalid Intcode value {:?} at token {}",
+            self.token, self.index
+        )
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+#[derive(Debug)]
+pub enum LoadError {
+    Io(io::Error),
+    Parse(ParseError),
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "could not read program: {e}"),
+            Self::Parse(e) => write!(f, "could not parse program: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for LoadError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(e) => Some(e),
+            Self::Parse(e) => Some(e),
+        }
+    }
+}
+
+impl From<io::Error> for LoadError {
+    fn from(e: io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+impl From<ParseError> for LoadError {
+    fn from(e: ParseError) -> Self {
+        Self::Parse(e)
+    }
+}
+
+impl Program {
+    /// # Errors
+    ///
+    /// Returns a `ParseError` for the first comma-separated token that isn't an integer.
+    pub fn parse(inp: &str) -> Result<Self, ParseError> {
+        let inp = inp.trim();
+
+        if inp.is_empty() {
+            return Ok(Self::default());
+        }
+
+        inp.split(',')
+            .map(str::trim)
+            .enumerate()
+            .map(|(index, token)| {
+                token.parse().map_err(|_| ParseError {
+                    index,
+                    token: token.to_owned(),
+                })
+            })
+            .collect::<Result<_, _>>()
+            .map(Self)
+    }
+
+    /// # Errors
+    ///
+    /// Returns `LoadError::Io` if the file can't be read and `LoadError::Parse` if it isn't
+    /// a valid program.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, LoadError> {
+        let inp = std::fs::read_to_string(path)?;
+        Ok(Self::parse(&inp)?)
+    }
+
+    #[must_use]
+    pub fn into_vec(self) -> Vec<i64> {
+        self.0
+    }
+}
+
+impl FromStr for Program {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+impl From<Vec<i64>> for Program {
+    fn from(v: Vec<i64>) -> Self {
+        Self(v)
+    }
+}
+
+impl Deref for Program {
+    type Target = [i64];
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl Borrow<[i64]> for Program {
+    fn borrow(&self) -> &[i64] {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let program = Program::parse("1,0, 0,3 ,\t99\n").expect("valid program");
+        assert_eq!(&*program, &[1, 0, 0, 3, 99]);
+
+        assert_eq!(Program::parse("\n"), Ok(Program::default()));
+    }
+
+    #[test]
+    fn test_parse_error() {
+        assert_eq!(
+            Program::parse("1,0,x3,99"),
+            Err(ParseError {
+                index: 2,
+                token: String::from("x3")
+            })
+        );
+
+        assert_eq!(
+            Program::parse("1,,99"),
+            Err(ParseError {
+                index: 1,
+                token: String::new()
+            })
+        );
+    }
+}
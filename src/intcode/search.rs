@@ -0,0 +1,313 @@
+use std::convert::TryFrom;
+use std::fmt;
+use std::ops::RangeInclusive;
+
+use itertools::Itertools;
+
+use crate::intcode::disasm::Operand;
+use crate::intcode::parallel::{par_find, par_map};
+use crate::intcode::{IntCode, State, ADD, MEM_SIZE, MUL, RB, READ};
+
+// Candidates are enumerated this many at a time, so that memory use doesn't grow with the
+// size of the search space.
+const CHUNK_SIZE: usize = 1 << 16;
+
+// Patched programs may never halt, each candidate gets this many instructions.
+const MAX_INSTRUCTIONS: u64 = 1_000_000;
+
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub enum SearchError {
+    Address(usize),
+    TooLarge,
+}
+
+impl fmt::Display for SearchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Address(addr) => write!(f, "patched address {addr} is outside of memory"),
+            Self::TooLarge => write!(f, "too many assignments to enumerate"),
+        }
+    }
+}
+
+impl std::error::Error for SearchError {}
+
+// How a run of the program ended: `Halted` with the final `mem[0]`, or `Waiting` if it asked
+// for input, along with everything it wrote before.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct Outcome {
+    pub state: State,
+    pub outputs: Vec<i64>,
+}
+
+struct Space<'a> {
+    vm: IntCode,
+    cells: &'a [(usize, RangeInclusive<i64>)],
+    lens: Vec<usize>,
+    total: usize,
+}
+
+impl<'a> Space<'a> {
+    fn new(
+        program: &[i64],
+        cells: &'a [(usize, RangeInclusive<i64>)],
+    ) -> Result<Self, SearchError> {
+        if let Some(&(addr, _)) = cells.iter().find(|&&(addr, _)| addr >= MEM_SIZE) {
+            return Err(SearchError::Address(addr));
+        }
+
+        let lens = cells
+            .iter()
+            .map(|(_, range)| range_len(range))
+            .collect::<Option<Vec<_>>>()
+            .ok_or(SearchError::TooLarge)?;
+        let total = lens
+            .iter()
+            .try_fold(1usize, |acc, &len| acc.checked_mul(len))
+            .ok_or(SearchError::TooLarge)?;
+
+        Ok(Self {
+            vm: IntCode::new(program),
+            cells,
+            lens,
+            total,
+        })
+    }
+
+    fn chunks(&self) -> impl Iterator<Item = Vec<usize>> + '_ {
+        (0..self.total)
+            .step_by(CHUNK_SIZE)
+            .map(move |start| (start..self.total.min(start.saturating_add(CHUNK_SIZE))).collect())
+    }
+
+    // Value of every cell for the `idx`th assignment, counting with the last cell changing
+    // fastest.
+    fn assignment(&self, mut idx: usize) -> Vec<i64> {
+        let mut values = vec![0; self.cells.len()];
+
+        for (value, ((_, range), &len)) in values
+            .iter_mut()
+            .zip(self.cells.iter().zip(&self.lens))
+            .rev()
+        {
+            // Below `len`, which `range_len` got from a span that fits into an i64.
+            let offset = i64::try_from(idx % len).expect("Offset out of range!");
+            *value = range.start() + offset;
+            idx /= len;
+        }
+
+        values
+    }
+
+    // `None` if the program runs out of instructions or would crash the VM.
+    fn run(&self, values: &[i64]) -> Option<Outcome> {
+        let mut vm = self.vm.clone();
+        for (&(addr, _), &value) in self.cells.iter().zip(values) {
+            vm.init_ram(addr, value);
+        }
+
+        let mut outputs = Vec::new();
+        loop {
+            if vm.stats().instructions >= MAX_INSTRUCTIONS || faults(&vm) {
+                return None;
+            }
+
+            match vm.step() {
+                Some(State::Write(n)) => outputs.push(n),
+                Some(state) => return Some(Outcome { state, outputs }),
+                None => {}
+            }
+        }
+    }
+
+    fn check<P>(&self, idx: usize, predicate: &P) -> Option<Vec<i64>>
+    where
+        P: Fn(&Outcome) -> bool,
+    {
+        let values = self.assignment(idx);
+        let outcome = self.run(&values)?;
+        predicate(&outcome).then_some(values)
+    }
+}
+
+// Whether executing the instruction at `vpc` would panic: an unknown opcode or parameter
+// mode, an address outside of memory, a write to an immediate parameter, or overflowing
+// arithmetic. A read only returns `State::Waiting`, its operand is never looked at.
+fn faults(vm: &IntCode) -> bool {
+    let Some(instruction) = vm.current_instruction() else {
+        return true;
+    };
+    let op = instruction.opcode % 100;
+    if op == READ {
+        return false;
+    }
+
+    let mut values = Vec::new();
+    for (idx, operand) in instruction.operands.iter().enumerate() {
+        let addr = match *operand {
+            Operand::Immediate(_) if idx == 2 => return true,
+            Operand::Immediate(val) => {
+                values.push(val);
+                continue;
+            }
+            Operand::Position(addr) => Some(addr),
+            Operand::Relative(offset) => vm.rel_base.checked_add(offset),
+        };
+
+        match addr.and_then(|it| usize::try_from(it).ok()) {
+            Some(addr) if addr < MEM_SIZE => values.push(vm.mem[addr]),
+            _ => return true,
+        }
+    }
+
+    match op {
+        ADD => values[0].checked_add(values[1]).is_none(),
+        MUL => values[0].checked_mul(values[1]).is_none(),
+        RB => vm.rel_base.checked_add(values[0]).is_none(),
+        _ => false,
+    }
+}
+
+fn range_len(range: &RangeInclusive<i64>) -> Option<usize> {
+    if range.is_empty() {
+        return Some(0);
+    }
+
+    let span = range.end().checked_sub(*range.start())?;
+    usize::try_from(span).ok()?.checked_add(1)
+}
+
+/// Runs `program` for every combination of values of the patched cells until it halts or
+/// asks for input, and returns those for which `predicate` holds on the outcome.
+///
+/// Assignments list one value per cell, in the order of `cells`, and are returned in the
+/// order they are enumerated. Assignments that make the program run for more than
+/// `MAX_INSTRUCTIONS` or crash the VM never match.
+///
+/// # Errors
+///
+/// Returns `SearchError::Address` for a cell outside of memory and `SearchError::TooLarge`
+/// if the number of assignments doesn't fit into a `usize`.
+pub fn search<P>(
+    program: &[i64],
+    cells: &[(usize, RangeInclusive<i64>)],
+    predicate: P,
+) -> Result<Vec<Vec<i64>>, SearchError>
+where
+    P: Fn(&Outcome) -> bool + Sync,
+{
+    let space = Space::new(program, cells)?;
+
+    let found = space
+        .chunks()
+        .flat_map(|candidates| par_map(&candidates, |&idx| space.check(idx, &predicate)))
+        .flatten()
+        .collect_vec();
+
+    Ok(found)
+}
+
+/// Same as `search`, but stops at the first matching assignment.
+///
+/// # Errors
+///
+/// Fails like `search`.
+pub fn find<P>(
+    program: &[i64],
+    cells: &[(usize, RangeInclusive<i64>)],
+    predicate: P,
+) -> Result<Option<Vec<i64>>, SearchError>
+where
+    P: Fn(&Outcome) -> bool + Sync,
+{
+    let space = Space::new(program, cells)?;
+
+    let found = space.chunks().find_map(|candidates| {
+        par_find(&candidates, |&idx| space.check(idx, &predicate)).map(|(_, values)| values)
+    });
+
+    Ok(found)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // mem[0] = mem[5] * mem[6]
+    const PRODUCT: [i64; 7] = [2, 5, 6, 0, 99, 0, 0];
+
+    #[test]
+    fn test_search() {
+        let found = search(&PRODUCT, &[(5, 1..=6), (6, 1..=6)], |it| {
+            it.state == State::Halted(12)
+        });
+        assert_eq!(
+            found,
+            Ok(vec![vec![2, 6], vec![3, 4], vec![4, 3], vec![6, 2]])
+        );
+
+        // Writes mem[5], then mem[6].
+        let echo = [4, 5, 4, 6, 99, 0, 0];
+        let found = search(&echo, &[(5, -3..=3), (6, 0..=1)], |it| {
+            it.outputs.iter().sum::<i64>() == 3
+        });
+        assert_eq!(found, Ok(vec![vec![2, 1], vec![3, 0]]));
+
+        assert_eq!(
+            search(
+                &PRODUCT,
+                &[(5, RangeInclusive::new(1, 0)), (6, 1..=6)],
+                |_| true
+            ),
+            Ok(Vec::new())
+        );
+    }
+
+    #[test]
+    fn test_find() {
+        let found = find(&PRODUCT, &[(5, 1..=6), (6, 1..=6)], |it| {
+            it.state == State::Halted(12)
+        });
+        assert_eq!(found, Ok(Some(vec![2, 6])));
+
+        let found = find(&PRODUCT, &[(5, 1..=6), (6, 1..=6)], |it| {
+            it.state == State::Halted(7)
+        });
+        assert_eq!(found, Ok(None));
+    }
+
+    #[test]
+    fn test_faults() {
+        // Jumps to mem[2]: 0 loops forever, 1 and 2 run into opcode 0 and 4 into opcode 77.
+        let jump = [1105, 1, 0, 99, 77];
+        assert_eq!(search(&jump, &[(2, 0..=4)], |_| true), Ok(vec![vec![3]]));
+        assert_eq!(find(&jump, &[(2, 0..=2)], |_| true), Ok(None));
+
+        // Arbitrary opcodes and parameter modes, with addresses below and above memory.
+        let found = search(&[0, 0, -1, 6000, 99], &[(0, 0..=30_000)], |it| {
+            it.state == State::Halted(99)
+        });
+        assert_eq!(found, Ok(vec![vec![99]]));
+
+        // mem[0] = mem[5] * mem[5] overflows.
+        let square = [2, 5, 5, 0, 99, 0];
+        let found = search(&square, &[(5, i64::MAX - 1..=i64::MAX)], |_| true);
+        assert_eq!(found, Ok(Vec::new()));
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(
+            search(&PRODUCT, &[(MEM_SIZE, 0..=1)], |_| true),
+            Err(SearchError::Address(MEM_SIZE))
+        );
+        assert_eq!(
+            find(&PRODUCT, &[(5, i64::MIN..=i64::MAX)], |_| true),
+            Err(SearchError::TooLarge)
+        );
+        assert_eq!(
+            search(&PRODUCT, &[(5, 0..=i64::MAX), (6, 0..=i64::MAX)], |_| true),
+            Err(SearchError::TooLarge)
+        );
+    }
+}
@@ -0,0 +1,130 @@
+use std::path::Path;
+
+use itertools::Itertools;
+
+use crate::intcode::lines::{lines, LineError};
+use crate::intcode::{IntCode, MEM_SIZE};
+
+pub type SnapshotError = LineError;
+
+impl IntCode {
+    // Text format with one `key value` pair per line. Memory is stored up to the last
+    // non-zero cell, execution counters are not part of a snapshot.
+    #[must_use]
+    pub fn save(&self) -> String {
+        let len = self
+            .mem
+            .iter()
+            .rposition(|&it| it != 0)
+            .map_or(0, |it| it + 1);
+
+        format!(
+            "vpc {}\nrel_base {}\nhalted {}\nmem {}\n",
+            self.vpc,
+            self.rel_base,
+            self.is_halted,
+            self.mem[..len].iter().join(",")
+        )
+    }
+
+    /// # Errors
+    ///
+    /// Returns `LineError::Parse` for an unknown key or a value that doesn't fit the VM, and
+    /// `LineError::Missing` if one of the keys `save` writes is absent.
+    pub fn load(snapshot: &str) -> Result<Self, SnapshotError> {
+        let mut vpc = None;
+        let mut rel_base = None;
+        let mut halted = None;
+        let mut mem = None;
+
+        for line in lines(snapshot) {
+            let error = || line.error();
+            let (key, value) = line.key_value();
+
+            match key {
+                "vpc" => {
+                    vpc = Some(
+                        value
+                            .parse::<usize>()
+                            .ok()
+                            .filter(|&it| it < MEM_SIZE)
+                            .ok_or_else(error)?,
+                    );
+                }
+                "rel_base" => rel_base = Some(value.parse().map_err(|_| error())?),
+                "halted" => halted = Some(value.parse().map_err(|_| error())?),
+                "mem" => {
+                    let cells = value
+                        .split(',')
+                        .filter(|it| !it.trim().is_empty())
+                        .map(|it| it.trim().parse())
+                        .collect::<Result<Vec<i64>, _>>()
+                        .map_err(|_| error())?;
+
+                    if cells.len() > MEM_SIZE {
+                        return Err(error());
+                    }
+                    mem = Some(cells);
+                }
+                _ => return Err(error()),
+            }
+        }
+
+        let mut vm = Self::new(&mem.ok_or(SnapshotError::Missing("mem"))?);
+        vm.vpc = vpc.ok_or(SnapshotError::Missing("vpc"))?;
+        vm.rel_base = rel_base.ok_or(SnapshotError::Missing("rel_base"))?;
+        vm.is_halted = halted.ok_or(SnapshotError::Missing("halted"))?;
+
+        Ok(vm)
+    }
+
+    /// # Errors
+    ///
+    /// Returns `LineError::Io` if the file can't be written.
+    pub fn save_to_file<P: AsRef<Path>>(&self, path: P) -> Result<(), SnapshotError> {
+        Ok(std::fs::write(path, self.save())?)
+    }
+
+    /// # Errors
+    ///
+    /// Returns `LineError::Io` if the file can't be read, otherwise fails like `load`.
+    pub fn load_from_file<P: AsRef<Path>>(path: P) -> Result<Self, SnapshotError> {
+        Self::load(&std::fs::read_to_string(path)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode::State;
+
+    #[test]
+    fn test_round_trip() {
+        let mut vm = IntCode::new(&[109, 5, 3, 9, 204, 4, 99, 0, 0, 0]);
+        assert_eq!(vm.run(), State::Waiting);
+
+        let snapshot = vm.save();
+        assert_eq!(
+            snapshot,
+            "vpc 2\nrel_base 5\nhalted false\nmem 109,5,3,9,204,4,99\n"
+        );
+
+        let mut loaded = IntCode::load(&snapshot).expect("valid snapshot");
+        assert_eq!(loaded, vm);
+
+        loaded.input(42);
+        assert_eq!(loaded.run(), State::Write(42));
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert!(matches!(
+            IntCode::load("vpc 0\nrel_base x"),
+            Err(SnapshotError::Parse { line: 2, .. })
+        ));
+        assert!(matches!(
+            IntCode::load("vpc 0\nrel_base 0\nhalted false\n"),
+            Err(SnapshotError::Missing("mem"))
+        ));
+    }
+}
@@ -0,0 +1,333 @@
+use std::collections::BTreeSet;
+use std::convert::TryFrom;
+use std::fmt;
+
+use itertools::Itertools;
+
+use crate::intcode::program::Program;
+use crate::intcode::{
+    instruction_len, IntCode, State, ADD, EQ, HALT, JF, JT, LT, MEM_SIZE, MUL, RB, READ, WRITE,
+};
+
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub enum SpecialiseError {
+    // Length the residual program would need.
+    TooLarge(usize),
+}
+
+impl fmt::Display for SpecialiseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::TooLarge(len) => write!(
+                f,
+                "specialised program needs {len} cells, memory only has {MEM_SIZE}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for SpecialiseError {}
+
+// Addresses are below `MEM_SIZE`, so they always fit into a cell.
+#[allow(clippy::cast_possible_wrap)]
+const fn cell(addr: usize) -> i64 {
+    addr as i64
+}
+
+/// Folds `inputs` into `program`: the result behaves like `program` after it has consumed
+/// them, i.e. it writes the same outputs and then waits for the next (unknown) input.
+///
+/// The known prefix is evaluated concretely. If the program halts within it, only the
+/// outputs remain. Otherwise the memory snapshot is kept with unreachable code removed, and
+/// a small prologue appended to it replays the outputs, restores `rel_base` and jumps to
+/// where the original program stopped. Cells 0..3 hold the jump into that prologue until it
+/// restores them. If it can't be determined which cells the program touches, e.g. because it
+/// uses relative addressing, the prologue goes at the very end of memory instead, where only a
+/// program using all of it would find it.
+///
+/// # Errors
+///
+/// Returns `SpecialiseError::TooLarge` if the residual program doesn't fit into memory.
+pub fn specialise(program: &[i64], inputs: &[i64]) -> Result<Program, SpecialiseError> {
+    let mut vm = IntCode::new(program);
+    let mut inputs = inputs.iter();
+    let mut outputs = Vec::new();
+
+    loop {
+        match vm.run() {
+            State::Waiting => match inputs.next() {
+                Some(inp) => vm.input(*inp),
+                None => break,
+            },
+            State::Write(n) => outputs.push(n),
+            State::Halted(_) => break,
+        }
+    }
+
+    if vm.is_halted() {
+        let mut residual = outputs.iter().flat_map(|&out| vec![104, out]).collect_vec();
+        // Keep `State::Halted` reporting the same value.
+        residual.extend(&[1101, vm.mem[0], 0, 0, 99]);
+        if residual.len() > MEM_SIZE {
+            return Err(SpecialiseError::TooLarge(residual.len()));
+        }
+        return Ok(residual.into());
+    }
+
+    if vm.vpc == 0 && outputs.is_empty() && vm.mem[..program.len()] == *program {
+        return Ok(program.to_vec().into());
+    }
+
+    // Cells the program reads must stay in place even if they're zero, otherwise the
+    // prologue would end up on top of them.
+    let mut mem = vm.mem.clone();
+    let mut len = program.len();
+    let live = live_cells(&mem, vm.vpc);
+    if let Some(live) = &live {
+        for (addr, cell) in mem.iter_mut().enumerate() {
+            if !live.contains(&addr) {
+                *cell = 0;
+            }
+        }
+        len = live.iter().next_back().map_or(0, |it| it + 1);
+    }
+
+    let written = mem.iter().rposition(|&it| it != 0).map_or(0, |it| it + 1);
+    mem.truncate(len.max(written).max(3));
+
+    let mut prologue = outputs.iter().flat_map(|&out| vec![104, out]).collect_vec();
+    for (addr, &val) in mem[..3].iter().enumerate() {
+        prologue.extend(&[1101, val, 0, cell(addr)]);
+    }
+    if vm.rel_base != 0 {
+        prologue.extend(&[109, vm.rel_base]);
+    }
+    prologue.extend(&[1106, 0, cell(vm.vpc)]);
+
+    let entry = match live {
+        Some(_) => mem.len(),
+        None => MEM_SIZE.saturating_sub(prologue.len()),
+    };
+    if entry < mem.len() || entry + prologue.len() > MEM_SIZE {
+        return Err(SpecialiseError::TooLarge(mem.len() + prologue.len()));
+    }
+
+    mem[..3].copy_from_slice(&[1106, 0, cell(entry)]);
+    mem.resize(entry, 0);
+    mem.extend(prologue);
+    Ok(mem.into())
+}
+
+// Cells the program can still touch when it continues at `start`, either as code or as
+// position mode operands. Returns `None` whenever that can't be determined statically:
+// relative addressing, self-modifying code, or jump targets read from cells that are
+// written to. Only jumps on immediate conditions are resolved.
+fn live_cells(mem: &[i64], start: usize) -> Option<BTreeSet<usize>> {
+    let mut code = BTreeSet::new();
+    let mut data = BTreeSet::new();
+    let mut written = BTreeSet::new();
+    let mut assumed_constant = BTreeSet::new();
+    let mut visited = BTreeSet::new();
+    let mut todo = vec![start];
+
+    while let Some(pc) = todo.pop() {
+        if !visited.insert(pc) {
+            continue;
+        }
+
+        let opcode = *mem.get(pc)?;
+        let op = opcode % 100;
+        if ![ADD, MUL, READ, WRITE, JT, JF, LT, EQ, RB, HALT].contains(&op) {
+            return None;
+        }
+
+        let len = instruction_len(op);
+        code.extend(pc..pc + len);
+
+        let mut operands = Vec::new();
+        for param in 1..len {
+            let raw = *mem.get(pc + param)?;
+            let operand = match (opcode / 10i64.pow(param as u32 + 1)) % 10 {
+                0 => {
+                    let addr = usize::try_from(raw).ok()?;
+                    data.insert(addr);
+                    Some(addr)
+                }
+                1 => None,
+                _ => return None,
+            };
+            operands.push((raw, operand));
+        }
+
+        match op {
+            ADD | MUL | LT | EQ => {
+                written.insert(operands[2].1?);
+                todo.push(pc + len);
+            }
+            READ => {
+                written.insert(operands[0].1?);
+                todo.push(pc + len);
+            }
+            JT | JF => {
+                let target = match operands[1] {
+                    (_, Some(addr)) => {
+                        assumed_constant.insert(addr);
+                        *mem.get(addr)?
+                    }
+                    (raw, None) => raw,
+                };
+                let target = usize::try_from(target).ok()?;
+                let taken = match operands[0] {
+                    (cond, None) => Some((cond != 0) == (op == JT)),
+                    _ => None,
+                };
+
+                if taken != Some(false) {
+                    todo.push(target);
+                }
+                if taken != Some(true) {
+                    todo.push(pc + len);
+                }
+            }
+            HALT => {}
+            _ => todo.push(pc + len),
+        }
+    }
+
+    if !written.is_disjoint(&code) || !written.is_disjoint(&assumed_constant) {
+        return None;
+    }
+
+    code.extend(data);
+    Some(code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode::parallel::run_pure;
+
+    // Adds 10 * the first input to every following input, until one of them is zero.
+    #[rustfmt::skip]
+    const SCALE: [i64; 27] = [
+        3, 25,              //  0: read factor
+        1002, 25, 10, 25,   //  2: factor *= 10
+        3, 26,              //  6: read value
+        1006, 26, 24,       //  8: halt if value == 0
+        1, 25, 26, 26,      // 11: value += factor
+        4, 26,              // 15: output value
+        1105, 1, 6,         // 17: loop
+        104, -1,            // 20: never reached
+        99, 99,             // 22
+        99,                 // 24: halt
+        0, 0,
+    ];
+
+    #[test]
+    fn test_specialise() {
+        let residual = specialise(&SCALE, &[4, 1]).expect("fits in memory");
+
+        assert_eq!(run_pure(&SCALE, &[4, 1, 2, 3]), vec![41, 42, 43]);
+        assert_eq!(run_pure(&residual, &[2, 3]), vec![41, 42, 43]);
+        assert_eq!(run_pure(&residual, &[2, 0, 5]), vec![41, 42]);
+        assert_eq!(residual[20..24], [0, 0, 0, 0]);
+    }
+
+    fn load(inp: &str) -> Program {
+        Program::parse(inp).expect("valid program")
+    }
+
+    // Every way of splitting `inputs` into a folded prefix and a remainder fed at run time
+    // has to give the outputs of the original program.
+    fn assert_equivalent(program: &[i64], inputs: &[i64]) {
+        let expected = run_pure(program, inputs);
+
+        for split in 0..=inputs.len() {
+            let (known, rest) = inputs.split_at(split);
+            let residual = specialise(program, known).expect("fits in memory");
+            assert_eq!(run_pure(&residual, rest), expected, "{known:?} folded");
+        }
+    }
+
+    #[test]
+    fn test_equivalence() {
+        let day5 = load(include_str!("../../input/2019/day5.txt"));
+        assert_equivalent(&day5, &[1]);
+        assert_equivalent(&day5, &[5]);
+
+        let day7 = load(include_str!("../../input/2019/day7.txt"));
+        for phase in 0..10 {
+            for signal in &[0, 1, 17, 12345] {
+                assert_equivalent(&day7, &[phase, *signal]);
+            }
+        }
+
+        let day9 = load(include_str!("../../input/2019/day9.txt"));
+        assert_equivalent(&day9, &[1]);
+
+        // Day 5 example: outputs 999 below 8, 1000 for 8 and 1001 above 8.
+        let cmp = load(
+            "3,21,1008,21,8,20,1005,20,22,107,8,21,20,1006,20,31,1106,0,36,98,0,0,1002,21,125,20,\
+             4,20,1105,1,46,104,999,1105,1,46,1101,1000,1,20,4,20,1105,1,46,98,99",
+        );
+        for inp in 6..11 {
+            assert_equivalent(&cmp, &[inp]);
+        }
+    }
+
+    #[test]
+    fn test_halted() {
+        let residual = specialise(&SCALE, &[4, 1, 0]).expect("fits in memory");
+
+        assert_eq!(run_pure(&residual, &[]), vec![41]);
+        assert_eq!(IntCode::new(&residual).run(), State::Write(41));
+        assert_eq!(residual.len(), 7);
+    }
+
+    #[test]
+    fn test_no_inputs() {
+        assert_eq!(*specialise(&SCALE, &[]).expect("fits in memory"), SCALE);
+    }
+
+    #[test]
+    fn test_relative() {
+        // Reads a value into [rb + 0] and outputs it twice, with rb = 20.
+        let program = [109, 20, 203, 0, 204, 0, 204, 0, 99];
+        let residual = specialise(&program, &[]).expect("fits in memory");
+        assert_eq!(run_pure(&residual, &[3]), vec![3, 3]);
+        assert_eq!(IntCode::new(&residual).run(), State::Waiting);
+
+        let program = [3, 12, 109, 20, 203, 0, 204, 0, 204, 0, 99, 0, 0];
+        let residual = specialise(&program, &[7]).expect("fits in memory");
+        assert_eq!(run_pure(&residual, &[5]), vec![5, 5]);
+        assert_eq!(residual[12], 7);
+    }
+
+    #[test]
+    fn test_untouched_memory() {
+        // Reads into [20] and [rb + 11] with rb = 10, then outputs the untouched [22].
+        let program = [3, 20, 109, 10, 203, 11, 204, 12, 99];
+        assert_eq!(run_pure(&program, &[1, 2]), vec![0]);
+
+        let residual = specialise(&program, &[1]).expect("fits in memory");
+        assert_eq!(run_pure(&residual, &[2]), vec![0]);
+        assert_eq!(residual.len(), MEM_SIZE);
+    }
+
+    #[test]
+    fn test_too_large() {
+        // Reads into the last cell of memory and outputs it after waiting for more input.
+        let top = cell(MEM_SIZE - 1);
+        let program = [3, top, 3, 0, 4, top, 99];
+        assert!(matches!(
+            specialise(&program, &[7]),
+            Err(SpecialiseError::TooLarge(_))
+        ));
+
+        // Too many outputs to replay.
+        let echo = [3, 7, 4, 7, 1105, 1, 0, 0];
+        let residual = specialise(&echo, &[7]).expect("fits in memory");
+        assert_eq!(run_pure(&residual, &[8, 9]), vec![7, 8, 9]);
+        assert!(specialise(&echo, &vec![7; MEM_SIZE / 2]).is_err());
+    }
+}
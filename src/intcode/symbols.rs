@@ -0,0 +1,177 @@
+use std::ops::Range;
+use std::path::Path;
+
+use crate::intcode::lines::{lines, LineError};
+
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub enum SymbolKind {
+    Function,
+    Variable,
+    // Length-prefixed strings, one after another.
+    StringTable,
+}
+
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct Symbol {
+    pub kind: SymbolKind,
+    pub range: Range<usize>,
+    pub name: String,
+}
+
+#[derive(PartialEq, Eq, Clone, Debug, Default)]
+pub struct SymbolTable {
+    symbols: Vec<Symbol>,
+}
+
+pub type SymbolError = LineError;
+
+fn parse_symbol(line: &str) -> Option<Symbol> {
+    let mut parts = line.split_whitespace();
+
+    let kind = match parts.next()? {
+        "function" => SymbolKind::Function,
+        "variable" => SymbolKind::Variable,
+        "strings" => SymbolKind::StringTable,
+        _ => return None,
+    };
+
+    let addr = parts.next()?;
+    let range = if let Some((start, end)) = addr.split_once("..") {
+        start.parse().ok()?..end.parse().ok()?
+    } else {
+        let addr = addr.parse().ok()?;
+        addr..addr + 1
+    };
+
+    let name = parts.next()?.to_owned();
+    if parts.next().is_some() || range.is_empty() {
+        return None;
+    }
+
+    Some(Symbol { kind, range, name })
+}
+
+impl SymbolTable {
+    /// One symbol per line: `<function|variable|strings> <addr>[..<end>] <name>`, with `end`
+    /// exclusive. Everything after a `#` is a comment.
+    ///
+    /// # Errors
+    ///
+    /// Returns `SymbolError::Parse` for the first line that isn't a symbol.
+    pub fn parse(inp: &str) -> Result<Self, SymbolError> {
+        let mut symbols = Vec::new();
+
+        for line in lines(inp) {
+            symbols.push(parse_symbol(line.text).ok_or_else(|| line.error())?);
+        }
+
+        symbols.sort_by_key(|it| (it.range.start, it.range.end));
+        Ok(Self { symbols })
+    }
+
+    /// # Errors
+    ///
+    /// Returns `SymbolError::Io` if the file can't be read, and fails like `parse` otherwise.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, SymbolError> {
+        Self::parse(&std::fs::read_to_string(path)?)
+    }
+
+    #[must_use]
+    pub fn symbols(&self) -> &[Symbol] {
+        &self.symbols
+    }
+
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.symbols.is_empty()
+    }
+
+    // The innermost symbol covering `addr`.
+    #[must_use]
+    pub fn at(&self, addr: usize) -> Option<&Symbol> {
+        self.symbols
+            .iter()
+            .filter(|it| it.range.contains(&addr))
+            .max_by_key(|it| it.range.start)
+    }
+
+    pub fn starting_at(&self, addr: usize) -> impl Iterator<Item = &Symbol> + '_ {
+        self.symbols.iter().filter(move |it| it.range.start == addr)
+    }
+
+    // `name` or `name+offset` for addresses covered by a symbol.
+    #[must_use]
+    pub fn name(&self, addr: usize) -> Option<String> {
+        self.at(addr).map(|it| match addr - it.range.start {
+            0 => it.name.clone(),
+            offset => format!("{}+{offset}", it.name),
+        })
+    }
+
+    // The symbolic name if there is one, the plain address otherwise.
+    #[must_use]
+    pub fn label(&self, addr: usize) -> String {
+        self.name(addr).unwrap_or_else(|| addr.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SYMBOLS: &str = "\
+# comment
+function 16 double
+variable 100..102 result   # two cells
+strings 30..40 messages
+";
+
+    #[test]
+    fn test_parse() {
+        let table = SymbolTable::parse(SYMBOLS).expect("valid symbol file");
+
+        assert_eq!(
+            table.symbols()[0],
+            Symbol {
+                kind: SymbolKind::Function,
+                range: 16..17,
+                name: String::from("double"),
+            }
+        );
+        assert_eq!(table.symbols()[1].kind, SymbolKind::StringTable);
+        assert_eq!(table.symbols()[2].range, 100..102);
+
+        assert!(matches!(
+            SymbolTable::parse("function 1"),
+            Err(SymbolError::Parse { line: 1, .. })
+        ));
+        assert!(matches!(
+            SymbolTable::parse("label 1 x"),
+            Err(SymbolError::Parse { line: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn test_names() {
+        let table = SymbolTable::parse(SYMBOLS).expect("valid symbol file");
+
+        assert_eq!(table.label(16), "double");
+        assert_eq!(table.label(17), "17");
+        assert_eq!(table.label(101), "result+1");
+        assert_eq!(
+            table.at(35).map(|it| it.kind),
+            Some(SymbolKind::StringTable)
+        );
+    }
+
+    #[test]
+    fn test_symbol_files() {
+        for inp in [
+            include_str!("../../symbols/day21.sym"),
+            include_str!("../../symbols/day25.sym"),
+        ] {
+            let table = SymbolTable::parse(inp).expect("valid symbol file");
+            assert!(!table.is_empty());
+        }
+    }
+}
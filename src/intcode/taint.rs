@@ -0,0 +1,204 @@
+use std::collections::{BTreeSet, HashMap};
+
+use crate::intcode::{IntCode, ParameterMode, State, ADD, EQ, JF, JT, LT, MUL, RB, WRITE};
+
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct TaintedOutput<L> {
+    pub value: i64,
+    pub labels: BTreeSet<L>,
+}
+
+// Runs an `IntCode` while tracking, for every memory cell, which labelled inputs its value
+// was derived from. Values flow through arithmetic, comparisons, relative base changes and
+// computed addresses. Control flow is only tracked when `track_branches` is set, in which
+// case everything written after a branch on a tainted condition inherits its labels.
+#[derive(Clone, Debug)]
+pub struct TaintedVm<L> {
+    vm: IntCode,
+    shadow: HashMap<usize, BTreeSet<L>>,
+    rel_base: BTreeSet<L>,
+    branches: BTreeSet<L>,
+    track_branches: bool,
+    outputs: Vec<TaintedOutput<L>>,
+}
+
+impl<L> TaintedVm<L>
+where
+    L: Ord + Clone,
+{
+    #[must_use]
+    pub fn new(vm: IntCode) -> Self {
+        Self {
+            vm,
+            shadow: HashMap::new(),
+            rel_base: BTreeSet::new(),
+            branches: BTreeSet::new(),
+            track_branches: false,
+            outputs: Vec::new(),
+        }
+    }
+
+    #[must_use]
+    pub const fn track_branches(mut self, track: bool) -> Self {
+        self.track_branches = track;
+        self
+    }
+
+    #[must_use]
+    pub const fn vm(&self) -> &IntCode {
+        &self.vm
+    }
+
+    #[must_use]
+    pub fn labels(&self, addr: usize) -> BTreeSet<L> {
+        self.shadow.get(&addr).cloned().unwrap_or_default()
+    }
+
+    #[must_use]
+    pub fn outputs(&self) -> &[TaintedOutput<L>] {
+        &self.outputs
+    }
+
+    pub fn input(&mut self, value: i64, label: L) {
+        let dest = self.vm.param_addr(1);
+        let mut labels = self.address_labels(1);
+        labels.insert(label);
+
+        self.vm.input(value);
+        self.set_labels(dest, labels);
+    }
+
+    pub fn run(&mut self) -> State {
+        loop {
+            let opcode = self.vm.mem[self.vm.vpc] % 100;
+
+            match opcode {
+                ADD | MUL | LT | EQ => {
+                    let mut labels = self.operand_labels(1);
+                    labels.extend(self.operand_labels(2));
+                    labels.extend(self.address_labels(3));
+
+                    let dest = self.vm.param_addr(3);
+                    self.vm.step();
+                    self.set_labels(dest, labels);
+                }
+                WRITE => {
+                    let mut labels = self.operand_labels(1);
+                    labels.extend(self.branches.iter().cloned());
+
+                    let state = self.vm.step();
+                    if let Some(State::Write(value)) = state {
+                        self.outputs.push(TaintedOutput { value, labels });
+                        return State::Write(value);
+                    }
+                }
+                RB => {
+                    let labels = self.operand_labels(1);
+                    self.vm.step();
+                    self.rel_base.extend(labels);
+                }
+                JT | JF => {
+                    if self.track_branches {
+                        let labels = self.operand_labels(1);
+                        self.branches.extend(labels);
+                    }
+
+                    self.vm.step();
+                }
+                _ => {
+                    if let Some(state) = self.vm.step() {
+                        return state;
+                    }
+                }
+            }
+        }
+    }
+
+    fn set_labels(&mut self, dest: Option<usize>, mut labels: BTreeSet<L>) {
+        if let Some(dest) = dest {
+            labels.extend(self.branches.iter().cloned());
+
+            if labels.is_empty() {
+                self.shadow.remove(&dest);
+            } else {
+                self.shadow.insert(dest, labels);
+            }
+        }
+    }
+
+    // Labels influencing which cell a parameter refers to.
+    fn address_labels(&self, param: i64) -> BTreeSet<L> {
+        let mut labels = self.labels(self.vm.vpc + param as usize);
+
+        if self.vm.get_param_mode(param) == ParameterMode::Relative {
+            labels.extend(self.rel_base.iter().cloned());
+        }
+
+        labels
+    }
+
+    // Labels influencing the value a parameter reads.
+    fn operand_labels(&self, param: i64) -> BTreeSet<L> {
+        let mut labels = self.address_labels(param);
+
+        if let Some(addr) = self.vm.param_addr(param) {
+            labels.extend(self.labels(addr));
+        }
+
+        labels
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run_with(
+        program: &[i64],
+        inputs: &[(i64, char)],
+        track_branches: bool,
+    ) -> Vec<TaintedOutput<char>> {
+        let mut vm = TaintedVm::new(IntCode::new(program)).track_branches(track_branches);
+        let mut inputs = inputs.iter();
+
+        loop {
+            match vm.run() {
+                State::Waiting => {
+                    let (value, label) = inputs.next().expect("enough inputs");
+                    vm.input(*value, *label);
+                }
+                State::Write(_) => {}
+                State::Halted(_) => return vm.outputs().to_vec(),
+            }
+        }
+    }
+
+    #[test]
+    fn test_data_flow() {
+        // out(a + b), out(c), out(7)
+        let program = [3, 20, 3, 21, 3, 22, 1, 20, 21, 23, 4, 23, 4, 22, 104, 7, 99];
+        let outputs = run_with(&program, &[(1, 'a'), (2, 'b'), (3, 'c')], false);
+
+        let labels = outputs
+            .iter()
+            .map(|it| it.labels.iter().collect::<String>())
+            .collect::<Vec<_>>();
+        assert_eq!(labels, vec!["ab", "c", ""]);
+        assert_eq!(outputs[0].value, 3);
+    }
+
+    #[test]
+    fn test_branches() {
+        // out(1) if a == 8 else out(0)
+        let program = [
+            3, 12, 1008, 12, 8, 12, 1005, 12, 13, 104, 0, 99, 0, 104, 1, 99,
+        ];
+
+        assert_eq!(run_with(&program, &[(8, 'a')], false)[0].value, 1);
+        assert_eq!(run_with(&program, &[(8, 'a')], false)[0].labels.len(), 0);
+        assert_eq!(
+            run_with(&program, &[(8, 'a')], true)[0].labels,
+            std::iter::once('a').collect()
+        );
+    }
+}
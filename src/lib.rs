@@ -7,29 +7,30 @@
     clippy::cast_sign_loss
 )]
 
-mod intcode;
+pub mod intcode;
+mod render;
 
 mod day01;
 mod day02;
-mod day03;
-mod day04;
+pub mod day03;
+pub mod day04;
 mod day05;
 mod day06;
 mod day07;
 mod day08;
 mod day09;
 mod day10;
-mod day11;
+pub mod day11;
 mod day12;
-mod day13;
+pub mod day13;
 mod day14;
-mod day15;
+pub mod day15;
 mod day16;
 mod day17;
 mod day18;
-mod day19;
+pub mod day19;
 mod day20;
-mod day21;
+pub mod day21;
 mod day22;
 mod day23;
 mod day24;
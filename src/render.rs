@@ -0,0 +1,129 @@
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::hash::Hash;
+use std::io::Write;
+use std::time::{Duration, Instant};
+
+const ENV_FLAG: &str = "AOC_RENDER";
+
+// Rendering is opt-in: set `AOC_RENDER=1` to watch the grid-based days in the terminal.
+pub fn enabled() -> bool {
+    std::env::var_os(ENV_FLAG).is_some_and(|it| !it.is_empty() && it != "0")
+}
+
+pub struct Renderer<T> {
+    glyphs: HashMap<T, char>,
+    fallback: char,
+    min_frame_time: Duration,
+    last_frame: Option<Instant>,
+    drawn_lines: usize,
+}
+
+impl<T> Renderer<T>
+where
+    T: Copy + Eq + Hash,
+{
+    pub fn new(glyphs: &[(T, char)], max_fps: u32) -> Self {
+        Self {
+            glyphs: glyphs.iter().copied().collect(),
+            fallback: '?',
+            min_frame_time: Duration::from_secs(1) / max_fps.max(1),
+            last_frame: None,
+            drawn_lines: 0,
+        }
+    }
+
+    pub const fn with_fallback(mut self, fallback: char) -> Self {
+        self.fallback = fallback;
+        self
+    }
+
+    pub fn frame(&self, map: &[Vec<T>], status: &str) -> String {
+        let mut result = String::new();
+
+        for row in map {
+            result.extend(
+                row.iter()
+                    .map(|it| self.glyphs.get(it).copied().unwrap_or(self.fallback)),
+            );
+            result.push('\n');
+        }
+
+        if !status.is_empty() {
+            result.push_str(status);
+            result.push('\n');
+        }
+
+        result
+    }
+
+    // Whether `draw` would redraw right now. Lets callers skip building a map that would be
+    // thrown away.
+    pub fn is_due(&self) -> bool {
+        self.last_frame
+            .is_none_or(|last| last.elapsed() >= self.min_frame_time)
+    }
+
+    // Redraws the map in place, unless the previous frame was drawn too recently.
+    pub fn draw(&mut self, map: &[Vec<T>], status: &str) {
+        if !self.is_due() {
+            return;
+        }
+
+        self.last_frame = Some(Instant::now());
+        self.present(map, status);
+    }
+
+    // Draws the final frame regardless of the frame rate cap.
+    pub fn finish(&mut self, map: &[Vec<T>], status: &str) {
+        self.present(map, status);
+        self.drawn_lines = 0;
+    }
+
+    fn present(&mut self, map: &[Vec<T>], status: &str) {
+        let frame = self.frame(map, status);
+        let mut out = String::new();
+
+        if self.drawn_lines > 0 {
+            // Move the cursor back up to where the previous frame started.
+            let _ = write!(out, "\x1b[{}A", self.drawn_lines);
+        }
+
+        for line in frame.lines() {
+            out.push_str("\x1b[2K");
+            out.push_str(line);
+            out.push('\n');
+        }
+
+        self.drawn_lines = frame.lines().count();
+
+        let stdout = std::io::stdout();
+        let mut lock = stdout.lock();
+        // Rendering is best-effort, a closed terminal shouldn't abort the solver.
+        let _ = lock.write_all(out.as_bytes());
+        let _ = lock.flush();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frame() {
+        let renderer = Renderer::new(&[(0, ' '), (1, '#')], 30).with_fallback('.');
+        let map = vec![vec![1, 0, 1], vec![0, 2, 0]];
+
+        assert_eq!(renderer.frame(&map, ""), "# #\n . \n");
+        assert_eq!(renderer.frame(&map, "Score: 3"), "# #\n . \nScore: 3\n");
+    }
+
+    #[test]
+    fn test_is_due() {
+        let mut renderer = Renderer::new(&[(0, ' ')], 1);
+        assert!(renderer.is_due());
+
+        renderer.last_frame = Some(Instant::now());
+        assert!(!renderer.is_due());
+    }
+}